@@ -1,11 +1,7 @@
 use std::ops::*;
-use std::hash::{Hash, Hasher};
-use std::collections::HashSet;
-use std::collections::hash_map::DefaultHasher;
 
-//use num::{Integer, Signed};
-
-type Val = isize;
+mod nbody;
+use nbody::{Body, Simulation, Val};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 struct Vec3 {
@@ -25,6 +21,7 @@ fn unit(v: Val) -> Val {
 	}
 }
 
+#[allow(dead_code)]
 fn to(v: Val, u: Val) -> Val {
 	if v > u {
 		-1
@@ -54,6 +51,7 @@ impl Vec3 {
 		}
 	}
 
+	#[allow(dead_code)]
 	fn to(self, rhs: Self) -> Self {
 		Vec3 {
 			x: to(self.x, rhs.x),
@@ -62,27 +60,6 @@ impl Vec3 {
 		}
 	}
 
-	fn abssum(&self) -> Val {
-		self.x.abs() + self.y.abs() + self.z.abs()
-	}
-
-	fn keep(&mut self, i: usize) {
-		match i {
-			0 => {
-				self.y = 0;
-				self.z = 0;
-			},
-			1 => {
-				self.x = 0;
-				self.z = 0;
-			},
-			2 => {
-				self.x = 0;
-				self.y = 0;
-			},
-			_ => panic!(),
-		}
-	}
 }
 
 impl Add<Vec3> for Vec3 {
@@ -148,165 +125,32 @@ impl Moon {
 			vel: Velocity(Vec3::zero()),
 		}
 	}
-
-	fn potential_energy(&self) -> Val {
-		self.pos.0.abssum()
-	}
-
-	fn kinetic_energy(&self) -> Val {
-		self.vel.0.abssum()
-	}
-}
-
-#[derive(Hash)]
-struct System {
-	moons: Vec<Moon>,
-}
-
-#[derive(PartialEq, Debug)]
-struct Change {
-	i: usize,
-	from: usize,
-	v: Vec3,
-}
-
-fn changes_for_moons(
-	(m_i, i): (&Moon, usize),
-	(m_j, j): (&Moon, usize),
-) -> Vec<Change> {
-	let diff = m_j.pos.0.to(m_i.pos.0); //.unit();
-	let mut changes = Vec::new();
-
-	changes.push(Change {
-		i,
-		from: j,
-		v: -diff,
-	});
-	/*changes.push(Change {
-		i: j,
-		from: i,
-		v: diff,
-	});*/
-
-	changes
-}
-
-fn apply_changes(moons: &mut Vec<Moon>, changes: &Vec<Change>) {
-	for Change { i, from: _, v } in changes {
-		/*if *i == 0 {
-			println!("moons[{}].vel += {:?} (from {})", *i, *v, *from);
-		}*/
-
-		moons[*i].vel.0 += *v;
-	}
-
-	//for moon in moons {
 }
 
-impl System {
-	fn step(&mut self) {
-		self.apply_gravity();
-		self.apply_velocity();
-		//self.time += 1;
-	}
-
-	fn apply_gravity(&mut self) {
-		let mut changes = Vec::new();
-
-		for (i, moon_i) in (&self.moons).iter().enumerate() {
-			for (j, moon_j) in (&self.moons).iter().enumerate() {
-				if i == j {
-					continue;
-				}
-
-				//println!("{} influencing {}", i, j);
-
-				changes.extend(
-					changes_for_moons(
-						(moon_i, i),
-						(moon_j, j)));
-			}
+impl From<&Moon> for Body {
+	fn from(m: &Moon) -> Self {
+		Body {
+			pos: vec![m.pos.0.x, m.pos.0.y, m.pos.0.z],
+			vel: vec![m.vel.0.x, m.vel.0.y, m.vel.0.z],
 		}
-
-		apply_changes(&mut self.moons, &changes);
-	}
-
-	fn apply_velocity(&mut self) {
-		for moon in self.moons.iter_mut() {
-			moon.pos.0 += moon.vel.0;
-		}
-	}
-
-	fn energy(&self) -> Val {
-		self.moons
-			.iter()
-			.map(|m| m.kinetic_energy() * m.potential_energy())
-			.sum()
-	}
-}
-
-/*
-impl Hash for System {
-	fn hash<H: Hasher>(&self, state: &mut H)  {
-		state.upda
-	}
-}
-*/
-
-#[allow(dead_code)]
-fn part1() {
-	let mut sys = System {
-		moons: vec![
-			Moon::new(-9, -1, -1),
-			Moon::new(2, 9, 5),
-			Moon::new(10, 18, -12),
-			Moon::new(-6, 15, -7),
-		],
-	};
-
-	for _ in 1..=1000 {
-		sys.step();
 	}
-
-	println!("{}", sys.energy());
-}
-
-fn gethash(s: &System) -> u64 {
-	let mut hasher = DefaultHasher::new();
-	s.hash(&mut hasher);
-	hasher.finish()
-
 }
 
-fn divisible(a: Val, b: Val) -> bool {
-	let div = (a as f64) / (b as f64);
-
-	return div == div.round();
-}
-
-fn lcm(ents: &[Val]) -> Val {
-	// broke, used online calculator
-	for i in (1..*ents.iter().max().expect("empty?")).rev() {
-		let mut can = true;
-
-		for &ent in ents {
-			if !divisible(ent, i) {
-				can = false;
-				break;
-			}
-		}
-
-		if can {
-			return i;
+impl From<&Body> for Moon {
+	fn from(b: &Body) -> Self {
+		Moon {
+			pos: Pos(Vec3::new(b.pos[0], b.pos[1], b.pos[2])),
+			vel: Velocity(Vec3::new(b.vel[0], b.vel[1], b.vel[2])),
 		}
 	}
-	panic!();
 }
 
-fn part2() {
-	let mut prev_states = HashSet::new();
+fn simulation(moons: &[Moon]) -> Simulation {
+	Simulation::new(&moons.iter().map(Body::from).collect::<Vec<_>>())
+}
 
-	let start = vec![
+fn input() -> Vec<Moon> {
+	vec![
 		// mine:
 		Moon::new(-9, -1, -1),
 		Moon::new(2, 9, 5),
@@ -318,48 +162,26 @@ fn part2() {
 		//Moon::new(2, -10, -7),
 		//Moon::new(4, -8, 8),
 		//Moon::new(3, 5, -1),
-	];
-
-	let mut iters = [0; 3];
-
-	for part in 0..3 {
-		let mut moons = start.clone();
-
-		// we can do this just for each individual axis, and then we have the time to reach
-		// that same axis state, for all axes. then we find the least common multiple of all
-		// those to find when they all happen to repeat at the same time, faster than the prior
-		// brute force method
-		for m in moons.iter_mut() {
-			m.pos.0.keep(part);
-			m.vel.0.keep(part);
-		}
-
-		let mut sys = System { moons };
-		let mut this_steps = 0;
-
-		for steps in 1.. {
-			prev_states.insert(gethash(&sys));
-
-			sys.step();
+	]
+}
 
-			if prev_states.get(&gethash(&sys)).is_some() {
-				//println!("match! after {} steps", steps);
-				this_steps = steps;
-				break;
-			}
+#[allow(dead_code)]
+fn part1() {
+	let sys = simulation(&input());
 
-			// got to at least 6960000
+	println!("{}", sys.energy_at(1000));
+}
 
-			/*if steps % 10000 == 0{
-				println!("steps {}, hashes: {}", steps, prev_states.len());
-			}*/
-		}
-		iters[part] = this_steps;
+fn part2() {
+	let sys = simulation(&input());
+
+	// each axis repeats on its own, so the whole system repeats at the least common
+	// multiple of those
+	println!("{:?}", sys.axis_periods());
+	match sys.period() {
+		Some(period) => println!("{}", period),
+		None => println!("never repeats"),
 	}
-
-	println!("{:?}", iters);
-	println!("{}", lcm(&iters));
-	//println!("{}", iters[0].lcm(iters[1]));
 }
 
 fn main() {
@@ -372,6 +194,10 @@ fn main() {
 mod tests {
 	use super::*;
 
+	fn moons(sim: &Simulation) -> Vec<Moon> {
+		sim.state().iter().map(Moon::from).collect()
+	}
+
 	#[test]
 	fn test_day12_diff() {
 		let callisto = Vec3::new(5, 0, 0); // should be -1 to vel
@@ -436,17 +262,15 @@ mod tests {
 
 	#[test]
 	fn test_day12_eg1() {
-		let mut sys = System {
-			moons: vec![
-				Moon::new(-1, 0, 2),
-				Moon::new(2, -10, -7),
-				Moon::new(4, -8, 8),
-				Moon::new(3, 5, -1),
-			],
-		};
+		let mut sys = simulation(&[
+			Moon::new(-1, 0, 2),
+			Moon::new(2, -10, -7),
+			Moon::new(4, -8, 8),
+			Moon::new(3, 5, -1),
+		]);
 
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 				Moon { pos: Pos(Vec3::new(-1,   0,  2)), vel: Velocity(Vec3::new( 0,  0,  0)) },
 				Moon { pos: Pos(Vec3::new( 2, -10, -7)), vel: Velocity(Vec3::new( 0,  0,  0)) },
@@ -459,7 +283,7 @@ mod tests {
 		// [0].y: -1 from [1], -1 from [2], +1 from [3] --> -1 (y vel)
 		// [0].z: -1 from [1], +1 from [2], -1 from [3] --> -1 (z vel)
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 				Moon { pos: Pos(Vec3::new( 2, -1,  1)), vel: Velocity(Vec3::new( 3, -1, -1)) },
 				Moon { pos: Pos(Vec3::new( 3, -7, -4)), vel: Velocity(Vec3::new( 1,  3,  3)) },
@@ -469,7 +293,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 5, -3, -1)), vel: Velocity(Vec3::new( 3, -2, -2)) },
 			Moon { pos: Pos(Vec3::new( 1, -2,  2)), vel: Velocity(Vec3::new(-2,  5,  6)) },
@@ -479,7 +303,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 5, -6, -1)), vel: Velocity(Vec3::new( 0, -3,  0)) },
 			Moon { pos: Pos(Vec3::new( 0,  0,  6)), vel: Velocity(Vec3::new(-1,  2,  4)) },
@@ -489,7 +313,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 2, -8,  0)), vel: Velocity(Vec3::new(-3, -2,  1)) },
 			Moon { pos: Pos(Vec3::new( 2,  1,  7)), vel: Velocity(Vec3::new( 2,  1,  1)) },
@@ -499,7 +323,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new(-1, -9,  2)), vel: Velocity(Vec3::new(-3, -1,  2)) },
 			Moon { pos: Pos(Vec3::new( 4,  1,  5)), vel: Velocity(Vec3::new( 2,  0, -2)) },
@@ -509,7 +333,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new(-1, -7,  3)), vel: Velocity(Vec3::new( 0,  2,  1)) },
 			Moon { pos: Pos(Vec3::new( 3,  0,  0)), vel: Velocity(Vec3::new(-1, -1, -5)) },
@@ -519,7 +343,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 2, -2,  1)), vel: Velocity(Vec3::new( 3,  5, -2)) },
 			Moon { pos: Pos(Vec3::new( 1, -4, -4)), vel: Velocity(Vec3::new(-2, -4, -4)) },
@@ -529,7 +353,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 5,  2, -2)), vel: Velocity(Vec3::new( 3,  4, -3)) },
 			Moon { pos: Pos(Vec3::new( 2, -7, -5)), vel: Velocity(Vec3::new( 1, -3, -1)) },
@@ -539,7 +363,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 5,  3, -4)), vel: Velocity(Vec3::new( 0,  1, -2)) },
 			Moon { pos: Pos(Vec3::new( 2, -9, -3)), vel: Velocity(Vec3::new( 0, -2,  2)) },
@@ -549,7 +373,7 @@ mod tests {
 
 		sys.step();
 		assert_eq!(
-			sys.moons,
+			moons(&sys),
 			vec![
 			Moon { pos: Pos(Vec3::new( 2,  1, -3)), vel: Velocity(Vec3::new(-3, -2,  1)) },
 			Moon { pos: Pos(Vec3::new( 1, -8,  0)), vel: Velocity(Vec3::new(-1,  1,  3)) },
@@ -0,0 +1,259 @@
+#![allow(dead_code)]
+
+// gravity only ever acts along each axis separately - a body's x velocity only depends on the
+// x positions - so the simulation is a set of independent one dimensional ones, which can be
+// stepped and searched for cycles on their own
+
+pub type Val = i64;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Axis {
+	pos: Vec<Val>,
+	vel: Vec<Val>,
+}
+
+impl Axis {
+	pub fn new(pos: Vec<Val>, vel: Vec<Val>) -> Self {
+		assert_eq!(pos.len(), vel.len());
+		Axis { pos, vel }
+	}
+
+	pub fn step(&mut self) {
+		let n = self.pos.len();
+
+		for i in 0..n {
+			for j in i + 1..n {
+				let pull = (self.pos[j] - self.pos[i]).signum();
+				self.vel[i] += pull;
+				self.vel[j] -= pull;
+			}
+		}
+
+		for (p, v) in self.pos.iter_mut().zip(&self.vel) {
+			*p += v;
+		}
+	}
+
+	// a step can be undone - the old positions are the new ones minus the velocities, and
+	// from those we get the old velocities - so no two states lead to the same one. the first
+	// state to come round again must then be the start, and there's no need to remember any
+	// of the others
+	//
+	// gravity pulls pairs equally and oppositely, so the total velocity never changes. if it
+	// isn't zero the bodies drift off together and never come back - None
+	pub fn period(&self) -> Option<u64> {
+		if self.vel.iter().sum::<Val>() != 0 {
+			return None;
+		}
+
+		let mut axis = self.clone();
+
+		for steps in 1.. {
+			axis.step();
+			if axis == *self {
+				return Some(steps);
+			}
+		}
+		unreachable!();
+	}
+
+	// steps `n` times, cutting it short if we come back round to where we started
+	pub fn advance(&mut self, n: u64) {
+		let start = self.clone();
+
+		for done in 1..=n {
+			self.step();
+
+			if *self == start {
+				for _ in 0..(n - done) % done {
+					self.step();
+				}
+				return;
+			}
+		}
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Body {
+	pub pos: Vec<Val>,
+	pub vel: Vec<Val>,
+}
+
+impl Body {
+	pub fn at(pos: Vec<Val>) -> Self {
+		let vel = vec![0; pos.len()];
+		Body { pos, vel }
+	}
+
+	pub fn energy(&self) -> Val {
+		let potential: Val = self.pos.iter().map(|p| p.abs()).sum();
+		let kinetic: Val = self.vel.iter().map(|v| v.abs()).sum();
+
+		potential * kinetic
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Simulation {
+	bodies: usize,
+	axes: Vec<Axis>,
+}
+
+impl Simulation {
+	// every body must have the same number of dimensions
+	pub fn new(bodies: &[Body]) -> Self {
+		let dims = bodies.first().map_or(0, |b| b.pos.len());
+		assert!(
+			bodies.iter().all(|b| b.pos.len() == dims && b.vel.len() == dims),
+			"bodies have differing dimensions");
+
+		let axes = (0..dims)
+			.map(|d| Axis::new(
+				bodies.iter().map(|b| b.pos[d]).collect(),
+				bodies.iter().map(|b| b.vel[d]).collect()))
+			.collect();
+
+		Simulation { bodies: bodies.len(), axes }
+	}
+
+	pub fn axes(&self) -> &[Axis] {
+		&self.axes
+	}
+
+	pub fn step(&mut self) {
+		for axis in &mut self.axes {
+			axis.step();
+		}
+	}
+
+	pub fn advance(&mut self, n: u64) {
+		for axis in &mut self.axes {
+			axis.advance(n);
+		}
+	}
+
+	pub fn state(&self) -> Vec<Body> {
+		(0..self.bodies)
+			.map(|i| Body {
+				pos: self.axes.iter().map(|a| a.pos[i]).collect(),
+				vel: self.axes.iter().map(|a| a.vel[i]).collect(),
+			})
+			.collect()
+	}
+
+	pub fn state_at(&self, n: u64) -> Vec<Body> {
+		let mut sim = self.clone();
+		sim.advance(n);
+		sim.state()
+	}
+
+	pub fn energy(&self) -> Val {
+		self.state().iter().map(Body::energy).sum()
+	}
+
+	pub fn energy_at(&self, n: u64) -> Val {
+		let mut sim = self.clone();
+		sim.advance(n);
+		sim.energy()
+	}
+
+	pub fn axis_periods(&self) -> Vec<Option<u64>> {
+		self.axes.iter().map(Axis::period).collect()
+	}
+
+	// steps until the whole system is back where it started. None if it never is, or that's
+	// too many to count
+	pub fn period(&self) -> Option<u64> {
+		let periods = self
+			.axis_periods()
+			.into_iter()
+			.map(|p| p.map(|p| p as i64))
+			.collect::<Option<Vec<_>>>()?;
+
+		numtheory::lcm_all(&periods).map(|p| p as u64)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sim(bodies: &[&[Val]]) -> Simulation {
+		Simulation::new(
+			&bodies
+				.iter()
+				.map(|b| Body::at(b.to_vec()))
+				.collect::<Vec<_>>())
+	}
+
+	fn eg1() -> Simulation {
+		sim(&[&[-1, 0, 2], &[2, -10, -7], &[4, -8, 8], &[3, 5, -1]])
+	}
+
+	fn eg2() -> Simulation {
+		sim(&[&[-8, -10, 0], &[5, 5, 10], &[2, -7, 3], &[9, -8, -3]])
+	}
+
+	#[test]
+	fn test_energy() {
+		assert_eq!(eg1().energy_at(10), 179);
+		assert_eq!(eg2().energy_at(100), 1940);
+	}
+
+	#[test]
+	fn test_period() {
+		assert_eq!(eg1().axis_periods(), vec![Some(18), Some(28), Some(44)]);
+		assert_eq!(eg1().period(), Some(2772));
+		assert_eq!(eg2().period(), Some(4686774924));
+	}
+
+	#[test]
+	fn test_drift() {
+		// moving off together along y, so that axis never repeats
+		let drifting = Simulation::new(&[
+			Body { pos: vec![0, 0], vel: vec![0, 1] },
+			Body { pos: vec![3, 2], vel: vec![0, 0] },
+		]);
+
+		assert_eq!(drifting.axis_periods(), vec![Some(8), None]);
+		assert_eq!(drifting.period(), None);
+
+		// still fine to step, just not to skip ahead by periods
+		let mut stepped = drifting.clone();
+		for _ in 0..20 {
+			stepped.step();
+		}
+		assert_eq!(drifting.state_at(20), stepped.state());
+	}
+
+	#[test]
+	fn test_state_at() {
+		// five bodies in two dimensions
+		let start = sim(&[&[-1, -7], &[6, 8], &[1, 2], &[-1, -6], &[1, -5]]);
+
+		let mut stepped = start.clone();
+		for n in 0..50 {
+			assert_eq!(start.state_at(n), stepped.state(), "step {}", n);
+			stepped.step();
+		}
+
+		assert_eq!(start.axis_periods(), vec![Some(2650), Some(630)]);
+		let period = start.period().unwrap();
+		assert_eq!(period, 166950);
+		assert_eq!(start.state_at(period), start.state());
+		assert_eq!(start.state_at(period * 3 + 17), start.state_at(17));
+
+		// far past the point we could get to by stepping
+		let eg2 = eg2();
+		assert_eq!(eg2.state_at(4686774924 + 100), eg2.state_at(100));
+	}
+
+	#[test]
+	fn test_empty() {
+		let empty = sim(&[]);
+		assert_eq!(empty.state(), vec![]);
+		assert_eq!(empty.energy_at(10), 0);
+		assert_eq!(empty.period(), Some(1));
+	}
+}
@@ -1,390 +1,28 @@
-#![allow(unused_variables)]
-use std::collections::HashMap;
 use std::fs;
 
-const USE_MUL: bool = true;
-const TRILLION: usize = 1000000000000;
-const DEBUG: bool = false;
+mod reactions;
+use reactions::Reactions;
 
-#[derive(PartialEq, Eq, Hash, Clone)]
-struct Element(String);
+const TRILLION: u64 = 1000000000000;
 
-impl std::fmt::Debug for Element {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
-        write!(fmt, "{}", self.0)
-    }
-}
-
-type Ingredient = (Element, usize);
-
-struct Reactions {
-    //src2dst: HashMap<Vec<Ingredient>, Ingredient>,
-    dst2src: HashMap<Element, (usize, Vec<Ingredient>)>,
-}
-
-/*
-fn printindent(n: usize) {
-    for _ in 0..n {
-        print!("  ");
-    }
-}
-*/
-
-fn ingredient_from_str(s: &str) -> Ingredient {
-    let parts = s.split(' ').filter(|s| !s.is_empty()).collect::<Vec<_>>();
-
-    if parts.len() != 2 {
-        eprintln!("parse ingredient_from_str: {:?}", parts);
-    }
-
-
-    assert_eq!(parts.len(), 2);
-    (
-        Element(String::from(parts[1])),
-        parts[0].parse().expect("parseint"),
-    )
-}
-
-impl Element {
-    fn new(s: &str) -> Self {
-        Element(s.into())
-    }
-}
-
-impl Reactions {
-    fn from(s: &str) -> Self {
-        let list = s
-            .split('\n')
-            .filter(|x| !x.trim().is_empty())
-            .map(|line| {
-                let parts = line.split(" => ").collect::<Vec<_>>();
-
-                if parts.len() != 2 {
-                    eprintln!("parse sides: {:?}", parts);
-                }
-
-                assert_eq!(parts.len(), 2); // .expect("no \"=>\" in {}", line);
-
-                let from = parts[0].split(", ");
-                let to = parts[1];
-
-                let from_ingredients = from.map(ingredient_from_str).collect::<Vec<_>>();
-
-                (from_ingredients, ingredient_from_str(to))
-            });
-
-        //let mut src2dst = HashMap::new();
-        let mut dst2src = HashMap::new();
-        for (from_ingredients, to) in list {
-            //let prev = src2dst.insert(from_ingredients, to);
-            //assert!(prev.is_none());
-
-            let prev = dst2src.insert(
-                to.0,
-                (
-                    to.1,
-                    from_ingredients,
-                )
-            );
-            assert!(prev.is_none());
-        }
-
-        Reactions { dst2src /*, src2dst*/ }
-    }
-
-    fn get(&self, key: &Element) -> Option<&(usize, Vec<Ingredient>)> {
-        self.dst2src.get(key)
-    }
-
-    /*
-    fn work_backwards_one(&self, req: &Ingredient, usize>, indent: usize) {
-        if self.take(req) {
-        }
-
-        let &(req_gained, ingredients) = match self.get(&req.0) {
-            Some(ref x) => {
-                printindent(indent);
-                println!("{:?} --> {:?}", req.0, x);
-                x
-            },
-            None => {
-                // can't get it, must be raw, so we need 'req' many of it
-                raw.insert(
-                    req.0,
-                    raw.get(&req.0).map(|x| *x).unwrap_or(0) + req.1);
-                printindent(indent);
-                println!("{:?} --> NOTHING, returning {}", req, req.1);
-                return req.1;
-            }
-        };
-
-        let mul = 1;
-        while req_gained * mul < req.1 {
-            mul += 1;
-        }
-
-        let t = mul * req_gained * ingredients.iter().fold(
-            0,
-            |total, (elem, n)| {
-                total + self.work_backwards_one(
-                    &(elem.clone(), *n),
-                    raw,
-                    indent + 1)
-            });
-        printindent(indent);
-        println!("returning {} for {:?}", t, req);
-        t
-    }
-    */
-
-    fn iterate_internal(
-        &self,
-        tobuild: &mut HashMap<Element, usize>,
-        leftover: &mut HashMap<Element, usize>) {
-
-        let mut iterations = 0;
-        loop {
-            iterations += 1;
-            if iterations > 999 {
-                break;
-            }
-
-                    if DEBUG {
-            println!("tobuild: {:?} (leftover: {:?})", tobuild, leftover);
-                    }
-
-            let candidates = tobuild
-                .iter()
-                .filter(|(ref elem, _)| {
-                    self.get(&elem).is_some()
-                })
-                .collect::<Vec<_>>();
-
-            if candidates.len() == 0 {
-                break;
-            }
-
-            /*
-            // find the one with the least n
-            candidates.sort_by(
-                |a, b| {
-                    a.1.cmp(b.1)
-                });
-            */
-
-            let first = candidates[0];
-
-            let (ref selected, ref nreq) = first;
-
-            let (selected, nreq): (Element, usize) = ((*selected).clone(), **nreq);
-
-                    if DEBUG {
-            println!("  picked {:?} to build (need {})", selected, nreq);
-                    }
-
-            let &(nmade, ref ingredients) = self.get(&selected).unwrap(); /* {
-                Some(x) => x,
-                None => {
-                    println!("  is a root, added");
-                    assert_eq!(selected.0, "ORE");
-                    let already = roots.get(&selected).map(|x| *x).unwrap_or(0);
-                    roots.insert(selected.clone(), already + nreq);
-                    continue;
-                }
-            };*/
-
-            assert!(nmade > 0);
-            assert!(nreq > 0);
-
-            let mul = if USE_MUL {
-                let mut mul = 1;
-                while nmade * mul < nreq {
-                    mul += 1;
-                }
-                mul
-            } else {
-                1
-            };
-
-
-                    if DEBUG {
-            println!("  using recipe {:?} --> {} to make {} {:?} (need {} mul as nreq={})",
-                     ingredients, nmade,
-                     nmade * mul, selected,
-                     mul, nreq);
-                    }
-
-            let nmade = nmade * mul;
-
-            if nmade > nreq {
-                let waste = nmade - nreq;
-                // we've made plenty, remove
-                tobuild.remove(&selected);
-                leftover.insert(selected.clone(), leftover.get(&selected).map(|x| *x).unwrap_or(0) + waste);
-                    if DEBUG {
-                println!("  waste: {}, going in leftover", waste);
-                    }
-            } else {
-                let amount_still_tobuild = nreq - nmade;
-                if amount_still_tobuild > 0 {
-                    tobuild.insert(selected.clone(), amount_still_tobuild);
-                } else {
-                    tobuild.remove(&selected);
-                }
-            }
-
-            for (ref elem, mut nreq) in ingredients {
-                // scoped:
-                nreq *= mul;
-
-                let extralog = match leftover.get(&elem) {
-                    None => { false },
-                    Some(&already_have) => {
-                        let remaining;
-                        let to_use;
-
-                        if already_have > nreq {
-                            to_use = nreq;
-                            remaining = already_have - to_use;
-                        } else {
-                            to_use = already_have;
-                            remaining = 0;
-                        }
-
-                    if DEBUG {
-                        println!("  already have {} {:?}s, using {}, leaving {} in leftover",
-                                 already_have,
-                                 elem,
-                                 to_use,
-                                 remaining);
-                    }
-
-                        if remaining > 0 {
-                            leftover.insert(elem.clone(), remaining);
-                        } else {
-                            leftover.remove(&elem);
-                        }
-
-                        nreq -= to_use;
-                        true
-                    },
-                };
-
-                if extralog || true {
-                    if DEBUG {
-                    println!("  adding {} of {:?} to tobuild", nreq, elem);
-                    }
-                }
-
-                if nreq == 0 {
-                    continue;
-                }
-
-                let already_tobuild = tobuild.get(elem)
-                    .map(|x| *x)
-                    .unwrap_or(0);
-
-                tobuild.insert(elem.clone(), already_tobuild + nreq);
-            }
-        }
-    }
-
-    fn iterate(&self, req: &Ingredient,
-               leftover: &mut HashMap<Element, usize>) -> HashMap<Element, usize> {
-        let mut tobuild = HashMap::<Element, usize>::new();
-
-        //let mut roots = HashMap::<Element, usize>::new();
-
-        tobuild.insert(req.0.clone(), req.1);
-
-        self.iterate_internal(&mut tobuild, leftover);
-
-        tobuild
-    }
+fn part1(reactions: &Reactions) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", reactions.ore_for(1)?);
 
-    fn iterate2(&self) -> usize {
-        let mut ore = TRILLION;
-        let mut n = 0;
-        let mut leftover = HashMap::<Element, usize>::new();
-        let mut min = 1000000;
-        let mut max = 10000000;
-        let mut guess = min/2+max/2;
-
-        loop {
-            let roots = self.iterate(
-                &(Element::new("FUEL"), guess),
-                &mut leftover);
-
-            assert_eq!(roots.len(), 1);
-
-            let first = roots.iter().nth(0).unwrap();
-            //assert_eq!(first.0, &Element::new("ORE"));
-            let n = *first.1;
-
-            if n > TRILLION {
-                max = guess-1;
-            } else if n < TRILLION {
-                min = guess+1;
-            } else {
-                println!("exact! with {}", guess);
-                break guess;
-            }
-
-            println!("min {} max {} guess of {} fuel requires {} ore",
-                     min,max,guess,n);
-
-            //if min >= max-1 {
-            //    break guess;
-            //}
-
-            guess = max/2 + min/2;
-
-            /*
-            if *first.1 < ore {
-                ore -= *first.1;
-                n += 1;
-            } else {
-                break;
-            }
-            */
-        }
-
-        //n
-    }
+    Ok(())
 }
 
-/*#[allow(dead_code)]
-fn part1(s: &str) {
-    let reactions = Reactions::from(&s);
-    let roots = reactions.iterate(&(Element::new("FUEL"), 1));
-
-    println!("{:?}", roots);
-}*/
-
-fn part2(s: &str) {
-    let reactions = "
-        157 ORE => 5 NZVS
-        165 ORE => 6 DCFZ
-        44 XJWVT, 5 KHKGT, 1 QDVJ, 29 NZVS, 9 GPVTF, 48 HKGWZ => 1 FUEL
-        12 HKGWZ, 1 GPVTF, 8 PSHF => 9 QDVJ
-        179 ORE => 7 PSHF
-        177 ORE => 5 HKGWZ
-        7 DCFZ, 7 PSHF => 2 XJWVT
-        165 ORE => 2 GPVTF
-        3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT
-            ";
-
-    let reactions = Reactions::from(s);
+fn part2(reactions: &Reactions) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", reactions.max_fuel(TRILLION)?);
 
-    let tobuild = reactions.iterate2();
-
-    println!("{}", tobuild);
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let s = fs::read_to_string("./input")?;
-    //part1(&s);
-    part2(&s);
+    let reactions = Reactions::parse(&s)?;
+
+    part1(&reactions)?;
+    part2(&reactions)?;
 
     Ok(())
 }
@@ -392,6 +30,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use reactions::Element;
 
     #[test]
     fn parsing() {
@@ -405,9 +44,9 @@ mod tests {
             2 AB, 3 BC, 4 CA => 1 FUEL
         ";
 
-        let reactions = Reactions::from(reactions);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        assert_eq!(reactions.dst2src.len(), 7);
+        assert_eq!(reactions.len(), 7);
         /*
         assert_eq!(reactions.get(
                 &vec![((Element::new("ORE"), 9))]),
@@ -463,7 +102,6 @@ mod tests {
             assert_eq!(reactions.get(&Element::new("FUEL")), Some(&(1, vec![(Element::new("AB"), 2), (Element::new("BC"), 3), (Element::new("CA"), 4)])));
     }
 
-    /*
     #[test]
     fn eg1() {
         let reactions = "
@@ -476,14 +114,9 @@ mod tests {
             2 AB, 3 BC, 4 CA => 1 FUEL
         ";
 
-        let reactions = Reactions::from(reactions);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        let roots = reactions.iterate(&(Element::new("FUEL"), 1));
-
-        assert_eq!(roots.len(), 1);
-
-        let first = roots.iter().nth(0).unwrap();
-        assert_eq!(first, (&Element::new("ORE"), &165));
+        assert_eq!(reactions.ore_for(1), Ok(165));
     }
 
     #[test]
@@ -500,14 +133,9 @@ mod tests {
     3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT
         ";
 
-        let reactions = Reactions::from(reactions);
-
-        let roots = reactions.iterate(&(Element::new("FUEL"), 1));
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        assert_eq!(roots.len(), 1);
-
-        let first = roots.iter().nth(0).unwrap();
-        assert_eq!(first, (&Element::new("ORE"), &13312));
+        assert_eq!(reactions.ore_for(1), Ok(13312));
     }
 
     #[test]
@@ -527,14 +155,9 @@ mod tests {
     176 ORE => 6 VJHF
         ";
 
-        let reactions = Reactions::from(reactions);
-
-        let roots = reactions.iterate(&(Element::new("FUEL"), 1));
-
-        assert_eq!(roots.len(), 1);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        let first = roots.iter().nth(0).unwrap();
-        assert_eq!(first, (&Element::new("ORE"), &180697));
+        assert_eq!(reactions.ore_for(1), Ok(180697));
     }
 
     #[test]
@@ -559,16 +182,10 @@ mod tests {
     5 BHXH, 4 VRPVC => 5 LTCX
         ";
 
-        let reactions = Reactions::from(reactions);
-
-        let roots = reactions.iterate(&(Element::new("FUEL"), 1));
-
-        assert_eq!(roots.len(), 1);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        let first = roots.iter().nth(0).unwrap();
-        assert_eq!(first, (&Element::new("ORE"), &2210736));
+        assert_eq!(reactions.ore_for(1), Ok(2210736));
     }
-    */
 
     #[test]
     fn part2_eg2() {
@@ -584,12 +201,11 @@ mod tests {
     3 DCFZ, 7 NZVS, 5 HKGWZ, 10 PSHF => 8 KHKGT
         ";
 
-        let reactions = Reactions::from(reactions);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        reactions.iterate2();
+        assert_eq!(reactions.max_fuel(TRILLION), Ok(82892753));
     }
 
-    /*
     #[test]
     fn part2_eg3() {
         let reactions = "
@@ -607,9 +223,9 @@ mod tests {
     176 ORE => 6 VJHF
         ";
 
-        let reactions = Reactions::from(reactions);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        let fuel = reactions.iterate2();
+        let fuel = reactions.max_fuel(TRILLION).unwrap();
 
         assert_eq!(fuel, 5586022);
     }
@@ -636,11 +252,10 @@ mod tests {
     5 BHXH, 4 VRPVC => 5 LTCX
         ";
 
-        let reactions = Reactions::from(reactions);
+        let reactions = Reactions::parse(reactions).unwrap();
 
-        let fuel = reactions.iterate2();
+        let fuel = reactions.max_fuel(TRILLION).unwrap();
 
         assert_eq!(fuel, 460664);
     }
-    */
 }
@@ -0,0 +1,381 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+// the nanofactory's reactions, as a graph from each element to the ones it's made from. every
+// element but ORE is made by exactly one reaction, so working out what we need is a walk down
+// the graph in topological order: by the time we get to an element, everything which uses it
+// has been counted, so we know exactly how many batches to run
+
+pub const ORE: &str = "ORE";
+pub const FUEL: &str = "FUEL";
+
+#[derive(PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
+pub struct Element(pub String);
+
+impl Element {
+    pub fn new(s: &str) -> Self {
+        Element(s.into())
+    }
+}
+
+impl fmt::Debug for Element {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+pub type Ingredient = (Element, u64);
+
+#[derive(Debug, PartialEq)]
+pub enum ReactionError {
+    Parse { line: usize, msg: String },
+    Duplicate { line: usize, element: Element },
+    // an element needed along the way which no reaction makes
+    Missing(Element),
+    // elements which (eventually) need themselves, in order
+    Cycle(Vec<Element>),
+}
+
+impl fmt::Display for ReactionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReactionError::Parse { line, msg } => write!(f, "line {}: {}", line, msg),
+            ReactionError::Duplicate { line, element } => {
+                write!(f, "line {}: second reaction for {:?}", line, element)
+            }
+            ReactionError::Missing(element) => write!(f, "nothing makes {:?}", element),
+            ReactionError::Cycle(elements) => write!(f, "reaction cycle: {:?}", elements),
+        }
+    }
+}
+
+impl std::error::Error for ReactionError {}
+
+fn parse_ingredient(s: &str, line: usize) -> Result<Ingredient, ReactionError> {
+    let err = |msg: String| ReactionError::Parse { line, msg };
+
+    let parts = s.split_whitespace().collect::<Vec<_>>();
+    let (n, name) = match parts[..] {
+        [n, name] => (n, name),
+        _ => return Err(err(format!("expected \"<quantity> <element>\", got {:?}", s.trim()))),
+    };
+
+    let n = n.parse().map_err(|e| err(format!("quantity {:?}: {}", n, e)))?;
+    if n == 0 {
+        return Err(err(format!("zero quantity of {}", name)));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(err(format!("invalid element {:?}", name)));
+    }
+
+    Ok((Element::new(name), n))
+}
+
+// how much of the raw material a production took, and what was made but not used
+#[derive(Debug, PartialEq)]
+pub struct Production {
+    pub ore: u64,
+    pub leftover: HashMap<Element, u64>,
+}
+
+pub struct Reactions {
+    dst2src: HashMap<Element, (u64, Vec<Ingredient>)>,
+}
+
+impl Reactions {
+    // blank lines are skipped, but still counted for error line numbers
+    pub fn parse(s: &str) -> Result<Self, ReactionError> {
+        let mut dst2src = HashMap::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_no = i + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let (from, to) = match line.split(" => ").collect::<Vec<_>>()[..] {
+                [from, to] => (from, to),
+                _ => return Err(ReactionError::Parse {
+                    line: line_no,
+                    msg: format!("expected \"<ingredients> => <product>\", got {:?}", line.trim()),
+                }),
+            };
+
+            let from = from
+                .split(',')
+                .map(|s| parse_ingredient(s, line_no))
+                .collect::<Result<Vec<_>, _>>()?;
+            let (to, made) = parse_ingredient(to, line_no)?;
+
+            if to.0 == ORE {
+                return Err(ReactionError::Parse { line: line_no, msg: "ORE can't be made".into() });
+            }
+            if dst2src.contains_key(&to) {
+                return Err(ReactionError::Duplicate { line: line_no, element: to });
+            }
+
+            dst2src.insert(to, (made, from));
+        }
+
+        Ok(Reactions { dst2src })
+    }
+
+    pub fn get(&self, key: &Element) -> Option<&(u64, Vec<Ingredient>)> {
+        self.dst2src.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.dst2src.len()
+    }
+
+    // every element FUEL is made from, each coming before all of its ingredients, ending in ORE
+    pub fn order(&self) -> Result<Vec<Element>, ReactionError> {
+        enum Mark {
+            Visiting,
+            Done,
+        }
+
+        let mut marks = HashMap::new();
+        let mut order = vec![];
+        // elements on the current path, and which ingredient to look at next
+        let mut path = vec![(Element::new(FUEL), 0)];
+
+        while let Some((elem, next)) = path.last_mut() {
+            if *next == 0 {
+                marks.insert(elem.clone(), Mark::Visiting);
+            }
+
+            let ingredients: &[Ingredient] = match self.get(elem) {
+                Some((_, ingredients)) => ingredients,
+                None if elem.0 == ORE => &[],
+                None => return Err(ReactionError::Missing(elem.clone())),
+            };
+
+            if *next == ingredients.len() {
+                marks.insert(elem.clone(), Mark::Done);
+                order.push(elem.clone());
+                path.pop();
+                continue;
+            }
+
+            let ingredient = &ingredients[*next].0;
+            *next += 1;
+
+            match marks.get(ingredient) {
+                Some(Mark::Done) => {}
+                Some(Mark::Visiting) => {
+                    let start = path.iter().position(|(e, _)| e == ingredient).unwrap();
+                    let cycle = path[start..].iter().map(|(e, _)| e.clone()).collect();
+                    return Err(ReactionError::Cycle(cycle));
+                }
+                None => path.push((ingredient.clone(), 0)),
+            }
+        }
+
+        order.reverse();
+        Ok(order)
+    }
+
+    // elements with a reaction which FUEL doesn't need, sorted
+    pub fn unreachable(&self) -> Vec<Element> {
+        let mut seen = HashSet::new();
+        let mut todo = vec![Element::new(FUEL)];
+
+        while let Some(elem) = todo.pop() {
+            if !seen.insert(elem.clone()) {
+                continue;
+            }
+            if let Some((_, ingredients)) = self.get(&elem) {
+                todo.extend(ingredients.iter().map(|(e, _)| e.clone()));
+            }
+        }
+
+        let mut unreachable = self.dst2src
+            .keys()
+            .filter(|e| !seen.contains(e))
+            .cloned()
+            .collect::<Vec<_>>();
+        unreachable.sort();
+        unreachable
+    }
+
+    pub fn produce(&self, fuel: u64) -> Result<Production, ReactionError> {
+        let mut need = HashMap::new();
+        let mut leftover = HashMap::new();
+        need.insert(Element::new(FUEL), fuel);
+
+        for elem in self.order()? {
+            let (made, ingredients) = match self.get(&elem) {
+                Some(reaction) => reaction,
+                None => continue, // ORE
+            };
+
+            let wanted = need.get(&elem).copied().unwrap_or(0);
+            let batches = wanted.div_ceil(*made);
+
+            if batches * made > wanted {
+                leftover.insert(elem.clone(), batches * made - wanted);
+            }
+
+            for (ingredient, n) in ingredients {
+                *need.entry(ingredient.clone()).or_insert(0) += batches * n;
+            }
+        }
+
+        Ok(Production {
+            ore: need.get(&Element::new(ORE)).copied().unwrap_or(0),
+            leftover,
+        })
+    }
+
+    pub fn ore_for(&self, fuel: u64) -> Result<u64, ReactionError> {
+        Ok(self.produce(fuel)?.ore)
+    }
+
+    // the most fuel `budget` ore can make. ore needed only goes up with fuel, so double until
+    // we're over budget, then binary search
+    pub fn max_fuel(&self, budget: u64) -> Result<u64, ReactionError> {
+        let mut lo = 0;
+        let mut hi = 1;
+
+        while self.ore_for(hi)? <= budget {
+            lo = hi;
+            hi *= 2;
+        }
+
+        // ore_for(lo) fits, ore_for(hi) doesn't
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if self.ore_for(mid)? <= budget {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        Ok(lo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EG: &str = "
+        9 ORE => 2 A
+        8 ORE => 3 B
+        7 ORE => 5 C
+        3 A, 4 B => 1 AB
+        5 B, 7 C => 1 BC
+        4 C, 1 A => 1 CA
+        2 AB, 3 BC, 4 CA => 1 FUEL
+    ";
+
+    fn names(elements: &[Element]) -> Vec<&str> {
+        elements.iter().map(|e| e.0.as_str()).collect()
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |s| Reactions::parse(s).err().unwrap();
+
+        assert_eq!(
+            err("\n1 ORE => 1 A\n1 A -> 1 FUEL\n"),
+            ReactionError::Parse {
+                line: 3,
+                msg: "expected \"<ingredients> => <product>\", got \"1 A -> 1 FUEL\"".into(),
+            });
+        assert!(matches!(err("1 ORE, A => 1 B"), ReactionError::Parse { line: 1, .. }));
+        assert!(matches!(err("x ORE => 1 B"), ReactionError::Parse { line: 1, .. }));
+        assert!(matches!(err("1 ORE => 0 B"), ReactionError::Parse { line: 1, .. }));
+        assert!(matches!(err("1 ORE => 1 B!"), ReactionError::Parse { line: 1, .. }));
+        assert!(matches!(err("1 A => 1 ORE"), ReactionError::Parse { line: 1, .. }));
+        assert_eq!(
+            err("1 ORE => 1 A\n2 ORE => 1 A"),
+            ReactionError::Duplicate { line: 2, element: Element::new("A") });
+
+        assert_eq!(
+            err("1 ORE => 1 A\n1 A -> 1 FUEL\n").to_string(),
+            "line 2: expected \"<ingredients> => <product>\", got \"1 A -> 1 FUEL\"");
+    }
+
+    #[test]
+    fn order() {
+        let reactions = Reactions::parse(EG).unwrap();
+        let order = reactions.order().unwrap();
+
+        assert_eq!(order.len(), 8);
+        assert_eq!(order.first(), Some(&Element::new(FUEL)));
+        assert_eq!(order.last(), Some(&Element::new(ORE)));
+
+        // everything comes before what it's made from
+        let pos = |e: &Element| order.iter().position(|o| o == e).unwrap();
+        for elem in &order {
+            if let Some((_, ingredients)) = reactions.get(elem) {
+                for (ingredient, _) in ingredients {
+                    assert!(pos(elem) < pos(ingredient), "{:?} after {:?}", elem, ingredient);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cycles_and_missing() {
+        let reactions = Reactions::parse("
+            1 ORE, 1 C => 1 A
+            1 A => 1 B
+            2 B => 1 C
+            1 B => 1 FUEL
+        ").unwrap();
+        match reactions.order() {
+            Err(ReactionError::Cycle(cycle)) => {
+                assert_eq!(cycle.len(), 3);
+                assert!(names(&cycle).iter().all(|n| ["A", "B", "C"].contains(n)));
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+        assert!(reactions.ore_for(1).is_err());
+
+        let reactions = Reactions::parse("1 ORE => 1 A\n1 A, 1 Z => 1 FUEL").unwrap();
+        assert_eq!(reactions.order(), Err(ReactionError::Missing(Element::new("Z"))));
+    }
+
+    #[test]
+    fn unreachable() {
+        let reactions = Reactions::parse(&format!("{}\n1 ORE => 1 X\n1 X => 1 Y\n", EG)).unwrap();
+
+        assert_eq!(names(&reactions.unreachable()), vec!["X", "Y"]);
+        assert!(Reactions::parse(EG).unwrap().unreachable().is_empty());
+        assert_eq!(reactions.ore_for(1), Ok(165));
+    }
+
+    #[test]
+    fn leftovers() {
+        let reactions = Reactions::parse(EG).unwrap();
+        let production = reactions.produce(1).unwrap();
+
+        assert_eq!(production.ore, 165);
+
+        // 10 A, 23 B and 37 C are needed, made in batches of 2, 3 and 5
+        let mut leftover = production.leftover.into_iter().collect::<Vec<_>>();
+        leftover.sort();
+        assert_eq!(leftover, vec![(Element::new("B"), 1), (Element::new("C"), 3)]);
+    }
+
+    #[test]
+    fn budget() {
+        let reactions = Reactions::parse(EG).unwrap();
+
+        assert_eq!(reactions.max_fuel(0), Ok(0));
+        assert_eq!(reactions.max_fuel(164), Ok(0));
+        assert_eq!(reactions.max_fuel(165), Ok(1));
+
+        for budget in (0..5000).step_by(37) {
+            let fuel = reactions.max_fuel(budget).unwrap();
+            assert!(reactions.ore_for(fuel).unwrap() <= budget);
+            assert!(reactions.ore_for(fuel + 1).unwrap() > budget);
+        }
+    }
+}
@@ -1,10 +1,8 @@
 use std::fs;
-use std::io::Write;
 
 type N = i64;
 
 static BASE_PATTERN: &[N] = &[0, 1, 0, -1];
-const DEBUG: bool = false;
 
 struct RepeatIter<'a, X> {
     repeats: usize,
@@ -90,49 +88,98 @@ fn clamp(n: N) -> N {
     n.abs() % 10
 }
 
-fn phase(/*_phase: i32, */input: &[N], pattern: &[N]) -> Vec<N> {
-    let mut out = Vec::new();
-
-    for output_idx in 0..input.len() {
-        let mut simple = pattern
-            .iter()
-            .cloned()
-            .cycle();
-        let this_pattern = RepeatIter::new(
-            output_idx,
-            &mut simple
-        ).skip(1);
-
-        if DEBUG {
-            println!("--- {} ---", output_idx);
-        }
-        let sum = (0..input.len())
-            .zip(this_pattern)
-            .map(|(i, pat)| {
-                if DEBUG {
-                    println!("{} * {}", input[i], pat);
-                }
-                input[i] * pat
-            })
-            .sum();
+// reads the pattern out digit by digit for each output, kept to check `phase` against
+#[allow(dead_code)]
+fn phase_naive(input: &[N], pattern: &[N]) -> Vec<N> {
+    (0..input.len())
+        .map(|output_idx| {
+            let mut simple = pattern
+                .iter()
+                .cloned()
+                .cycle();
+            let this_pattern = RepeatIter::new(
+                output_idx,
+                &mut simple
+            ).skip(1);
+
+            clamp(input.iter().zip(this_pattern).map(|(x, pat)| x * pat).sum())
+        })
+        .collect()
+}
 
-        if DEBUG {
-            println!("summed: {}", sum);
-        }
-        out.push(clamp(sum));
+// output digit `i` uses each pattern entry for a run of `i + 1` input digits (one less for the
+// first run), so it's a sum of runs, each read from prefix sums. digit `i` has n / (i + 1)
+// runs, so that's n log n for the whole phase
+fn phase(input: &[N], pattern: &[N]) -> Vec<N> {
+    let n = input.len();
+
+    let mut prefix = Vec::with_capacity(n + 1);
+    prefix.push(0);
+    for &x in input {
+        prefix.push(prefix[prefix.len() - 1] + x);
     }
 
-    out
+    (0..n)
+        .map(|i| {
+            let run = i + 1;
+            let mut sum = 0;
+
+            // run `j` covers input[j * run - 1 .. (j + 1) * run - 1]
+            for j in 0.. {
+                let start = (j * run).saturating_sub(1);
+                if start >= n {
+                    break;
+                }
+                let end = ((j + 1) * run - 1).min(n);
+
+                let pat = pattern[j % pattern.len()];
+                if pat != 0 {
+                    sum += pat * (prefix[end] - prefix[start]);
+                }
+            }
+
+            clamp(sum)
+        })
+        .collect()
 }
 
-/*
-fn fft(nums: &[N]) -> Vec<N> {
-    //let pattern = [ ];
+// from halfway on, digit `i`'s pattern is zero before `i` and one from there to the end, so
+// each digit is the sum of itself and everything after it. digits before `i` never come
+// into it, so this works on just the tail of a signal
+fn phase_suffix(input: &mut [N]) {
+    let mut sum = 0;
+
+    for x in input.iter_mut().rev() {
+        sum = (sum + *x) % 10;
+        *x = sum;
+    }
+}
+
+// `len` digits from `offset`, after `phases` phases with the base pattern
+fn fft(signal: &[N], phases: usize, offset: usize, len: usize) -> Vec<N> {
+    let offset = offset.min(signal.len());
+    let len = len.min(signal.len() - offset);
+
+    if offset * 2 >= signal.len() {
+        fft_tail(signal[offset..].to_vec(), phases, len)
+    } else {
+        let mut a = signal.to_vec();
+        for _ in 0..phases {
+            a = phase(&a, BASE_PATTERN);
+        }
+        a[offset..offset + len].to_vec()
+    }
+}
 
-    //phase(0, nums, );
-    vec![]
+// `len` digits from the start of `tail`, after `phases` phases - only right if `tail` is the
+// second half (or less) of a signal
+fn fft_tail(mut tail: Vec<N>, phases: usize, len: usize) -> Vec<N> {
+    for _ in 0..phases {
+        phase_suffix(&mut tail);
+    }
+    tail.truncate(len);
+    tail
 }
-*/
 
 fn parse(s: &str) -> Vec<N> {
     let ns = s.chars()
@@ -144,69 +191,52 @@ fn parse(s: &str) -> Vec<N> {
     ns
 }
 
-#[allow(dead_code)]
+fn digits(ns: &[N]) -> String {
+    ns.iter().map(|n| n.to_string()).collect()
+}
+
 fn part1() -> Result<(), Box<dyn std::error::Error>> {
     let s = fs::read_to_string("./input")?;
     let s = s.trim();
-    let mut a = parse(&s);
-    for _ in 0..100 {
-        a = phase(&a, &BASE_PATTERN);
-    }
 
-    /*
-    let fin = a.iter()
-        .take(8)
-        .fold(|r, x|
-    println!("{}", */
-    for x in a.iter().take(8) {
-        print!("{} ", x);
-    }
-    println!();
+    println!("{}", digits(&fft(&parse(s), 100, 0, 8)));
 
     Ok(())
 }
 
-fn save(a: &[N]) -> Result<(), Box<dyn std::error::Error>> {
-    let mut f = std::fs::File::create("vec")?;
+const REPEATS: usize = 10000;
 
-    for &x in a {
-        writeln!(f, "{}", x)?;
+// the message in the real signal - the input repeated REPEATS times - at the offset given by
+// its first seven digits. the offset's normally in the second half, so only the signal from
+// there on is ever built
+fn real_message(s: &str, phases: usize) -> Result<Vec<N>, Box<dyn std::error::Error>> {
+    let offset: usize = s.get(0..7).ok_or("signal too short")?.parse()?;
+
+    let src = parse(s);
+    let total = REPEATS * src.len();
+    if offset >= total {
+        return Err(format!("offset {} is past the end of the signal", offset).into());
     }
 
-    Ok(())
+    if offset * 2 >= total {
+        let tail = (offset..total).map(|i| src[i % src.len()]).collect();
+        Ok(fft_tail(tail, phases, 8))
+    } else {
+        let signal = src.iter().cycle().take(total).cloned().collect::<Vec<_>>();
+        Ok(fft(&signal, phases, offset, 8))
+    }
 }
 
 fn part2() -> Result<(), Box<dyn std::error::Error>> {
     let s = fs::read_to_string("./input")?;
-    let s = s.trim();
-
-    let first7 = &s[0 .. 8];
-    let n: usize = first7.parse()?;
-    //let s = &s[8..];
-
-    let src = parse(&s);
-    let mut a = Vec::new();
-    a.extend(src.iter().cycle().take(10000 * src.len()));
-
-    for i in 0..100 {
-        a = phase(&a, &BASE_PATTERN);
-        println!("{}", i);
-    }
-
-    for x in a.iter().skip(n).take(8) {
-        print!("{}", x);
-    }
-    println!();
-
-    save(&a)?;
+    println!("{}", digits(&real_message(s.trim(), 100)?));
 
     Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    //part1()?;
-    //part2()?;
-    eg()?;
+    part1()?;
+    part2()?;
     Ok(())
 }
 
@@ -308,4 +338,70 @@ mod tests {
         x = phase(&x, BASE_PATTERN);
         assert_eq!(&x, &expected4);
     }
+
+    #[test]
+    fn test_phase_prefix_sums() {
+        let mut seed = 12345u32;
+        let signal = (0..200)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                (seed % 10) as N
+            })
+            .collect::<Vec<_>>();
+
+        for pattern in &[BASE_PATTERN, &[1, 2, 3], &[0, 0, 5], &[-3]] {
+            assert_eq!(phase(&signal, pattern), phase_naive(&signal, pattern), "{:?}", pattern);
+        }
+        assert_eq!(phase(&[], BASE_PATTERN), vec![]);
+    }
+
+    #[test]
+    fn test_suffix_matches_phase() {
+        let signal = parse("80871224585914546619083218645595");
+
+        let mut slow = signal.clone();
+        for _ in 0..10 {
+            slow = phase_naive(&slow, BASE_PATTERN);
+        }
+
+        for offset in 16..signal.len() {
+            assert_eq!(fft(&signal, 10, offset, 8), &slow[offset..(offset + 8).min(32)]);
+        }
+    }
+
+    #[test]
+    fn test_day16_part1() {
+        let egs = [
+            ("80871224585914546619083218645595", "24176176"),
+            ("19617804207202209144916044189917", "73745418"),
+            ("69317163492948606335995924319873", "52432133"),
+        ];
+
+        for (input, expected) in &egs {
+            assert_eq!(digits(&fft(&parse(input), 100, 0, 8)), *expected);
+        }
+    }
+
+    #[test]
+    fn test_day16_part2() {
+        let egs = [
+            ("03036732577212944063491565474664", "84462026"),
+            ("02935109699940807407585447034323", "78725270"),
+            ("03081770884921959731165446850517", "53553731"),
+        ];
+
+        for (input, expected) in &egs {
+            assert_eq!(digits(&real_message(input, 100).unwrap()), *expected);
+        }
+
+        // an offset in the first half still works, the slow way
+        let short = "0000001";
+        let signal = parse(short).repeat(REPEATS);
+        assert_eq!(real_message(short, 1).unwrap(), fft(&signal, 1, 1, 8));
+
+        assert!(real_message("9999999", 100).is_err());
+        assert!(real_message("123", 100).is_err());
+    }
 }
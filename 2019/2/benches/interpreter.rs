@@ -0,0 +1,58 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[path = "../src/lib.rs"]
+mod lib;
+use lib::{IntCodeMachine, Word};
+
+#[path = "../src/fast.rs"]
+mod fast;
+use fast::FastMachine;
+
+#[path = "../src/parse.rs"]
+mod parse;
+
+// day 9 part 2: one long-running machine
+fn day9(c: &mut Criterion) {
+    let bytes = parse::bytes("./input-day9").unwrap();
+
+    let mut group = c.benchmark_group("day9");
+    group.sample_size(10);
+
+    group.bench_function("interpreter", |b| b.iter(|| {
+        IntCodeMachine::new(&bytes, false).interpret_async(&mut vec![2])
+    }));
+    group.bench_function("fast", |b| b.iter(|| {
+        FastMachine::new(&bytes).interpret_async(&mut vec![2])
+    }));
+
+    group.finish();
+}
+
+// day 19 part 1: many short-lived machines
+fn day19(c: &mut Criterion) {
+    let bytes = parse::bytes("./input-day19").unwrap();
+    let coords = || (0..50).flat_map(|y| (0..50).map(move |x| vec![x as Word, y as Word]));
+
+    let mut group = c.benchmark_group("day19");
+    group.sample_size(10);
+
+    group.bench_function("interpreter", |b| b.iter(|| {
+        coords()
+            .map(|mut input| IntCodeMachine::new(&bytes, false).interpret_async(&mut input)[0])
+            .sum::<Word>()
+    }));
+    group.bench_function("fast", |b| {
+        let pristine = FastMachine::new(&bytes);
+
+        b.iter(|| {
+            coords()
+                .map(|mut input| pristine.clone().interpret_async(&mut input)[0])
+                .sum::<Word>()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, day9, day19);
+criterion_main!(benches);
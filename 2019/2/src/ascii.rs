@@ -1,4 +1,6 @@
-use crate::lib::{IntCodeMachine, Word};
+use std::io;
+
+use crate::lib::{IntCodeMachine, IntcodeInput, IntcodeOutput, Snapshot, Word};
 
 #[derive(Clone)]
 pub struct AsciiMachine {
@@ -12,21 +14,42 @@ impl AsciiMachine {
         }
     }
 
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        self.icm.snapshot().save(path)
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Ok(Self {
+            icm: IntCodeMachine::from_snapshot(&Snapshot::load(path)?, false),
+        })
+    }
+
     pub fn is_running(&self) -> bool {
         self.icm.is_running()
     }
 
     pub fn run_intcode_output(&mut self, input: String) -> Vec<Word> {
-        let mut inv: Vec<Word> = input
-            .chars()
-            .map(|c| c as _)
-            .collect();
-
-        self.icm.interpret_async(&mut inv)
+        self.icm.interpret_async(&mut input.chars())
     }
 
     pub fn run(&mut self, input: String) -> String {
-        to_string(&self.run_intcode_output(input))
+        let mut output = String::new();
+
+        self.icm.interpret_io(&mut input.chars(), &mut output);
+
+        output
+    }
+}
+
+impl IntcodeInput for std::str::Chars<'_> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.next().map(|c| c as _)
+    }
+}
+
+impl IntcodeOutput for String {
+    fn output(&mut self, w: Word) {
+        self.push(w as u8 as char);
     }
 }
 
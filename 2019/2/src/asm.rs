@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::lib::{Opcode, OPCODES, Word};
+
+// syntax, one statement per line:
+//   label:                 (may be followed by a statement)
+//   add [5], #-1, rb+2     position, immediate and relative-base operands
+//   jnz #1, #loop          operands may name labels, with an optional +/- offset
+//   data 1, 2, end-1       raw words
+//   ; comment
+
+#[derive(Debug, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+#[derive(Debug)]
+enum Expr {
+    Num(Word),
+    Label(String, Word),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    Position = 0,
+    Immediate = 1,
+    RelativeBase = 2,
+}
+
+enum Stmt {
+    Isn {
+        op: &'static Opcode,
+        params: Vec<(Mode, Expr)>,
+    },
+    Data(Vec<Expr>),
+}
+
+impl Stmt {
+    fn len(&self) -> usize {
+        match self {
+            Stmt::Isn { op, .. } => 1 + op.nparams,
+            Stmt::Data(values) => values.len(),
+        }
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {},
+        _ => return false,
+    }
+
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_num(s: &str) -> Result<Word, String> {
+    s.parse().map_err(|_| format!("invalid number \"{}\"", s))
+}
+
+fn parse_expr(s: &str) -> Result<Expr, String> {
+    let s = s.trim();
+
+    if let Ok(n) = s.parse() {
+        return Ok(Expr::Num(n));
+    }
+
+    let (name, offset) = match s.find(['+', '-']) {
+        Some(i) => (s[..i].trim(), parse_num(s[i..].replace(' ', "").trim_start_matches('+'))?),
+        None => (s, 0),
+    };
+
+    if !is_ident(name) {
+        return Err(format!("invalid expression \"{}\"", s));
+    }
+
+    Ok(Expr::Label(name.into(), offset))
+}
+
+fn parse_operand(s: &str) -> Result<(Mode, Expr), String> {
+    let s = s.trim();
+
+    if let Some(imm) = s.strip_prefix('#') {
+        Ok((Mode::Immediate, parse_expr(imm)?))
+    } else if let Some(pos) = s.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+        Ok((Mode::Position, parse_expr(pos)?))
+    } else if s == "rb" {
+        Ok((Mode::RelativeBase, Expr::Num(0)))
+    } else if s.starts_with("rb+") || s.starts_with("rb-") {
+        Ok((Mode::RelativeBase, parse_expr(s[2..].trim_start_matches('+'))?))
+    } else {
+        Err(format!("invalid operand \"{}\" (expected [pos], #imm or rb+off)", s))
+    }
+}
+
+fn split_list(s: &str) -> Vec<&str> {
+    if s.trim().is_empty() {
+        vec![]
+    } else {
+        s.split(',').collect()
+    }
+}
+
+fn parse_stmt(s: &str) -> Result<Stmt, String> {
+    let (mnemonic, rest) = match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], &s[i..]),
+        None => (s, ""),
+    };
+    let mnemonic = mnemonic.to_lowercase();
+
+    if mnemonic == "data" {
+        let values = split_list(rest)
+            .into_iter()
+            .map(parse_expr)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if values.is_empty() {
+            return Err("data needs at least one value".into());
+        }
+        return Ok(Stmt::Data(values));
+    }
+
+    let op = OPCODES
+        .iter()
+        .find(|op| op.mnemonic == mnemonic)
+        .ok_or_else(|| format!("unknown mnemonic \"{}\"", mnemonic))?;
+
+    let params = split_list(rest)
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if params.len() != op.nparams {
+        return Err(format!(
+                "{} takes {} operand(s), found {}",
+                op.mnemonic,
+                op.nparams,
+                params.len()));
+    }
+
+    if let (true, Some((Mode::Immediate, _))) = (op.writes, params.last()) {
+        return Err(format!("{} can't write to an immediate operand", op.mnemonic));
+    }
+
+    Ok(Stmt::Isn { op, params })
+}
+
+pub fn assemble(src: &str) -> Result<Vec<Word>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut stmts = Vec::new();
+    let mut addr = 0;
+
+    for (i, line) in src.lines().enumerate() {
+        let lineno = i + 1;
+        let err = |msg| AsmError { line: lineno, msg };
+
+        let mut line = match line.find(';') {
+            Some(i) => &line[..i],
+            None => line,
+        }.trim();
+
+        if let Some(i) = line.find(':') {
+            let name = line[..i].trim();
+            if !is_ident(name) {
+                return Err(err(format!("invalid label \"{}\"", name)));
+            }
+            if labels.insert(name.to_string(), addr as Word).is_some() {
+                return Err(err(format!("duplicate label \"{}\"", name)));
+            }
+            line = line[i + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let stmt = parse_stmt(line).map_err(err)?;
+        addr += stmt.len();
+        stmts.push((lineno, stmt));
+    }
+
+    let mut words = Vec::with_capacity(addr);
+
+    for (lineno, stmt) in stmts {
+        let resolve = |expr: &Expr| match expr {
+            Expr::Num(n) => Ok(*n),
+            Expr::Label(name, offset) => labels
+                .get(name)
+                .map(|addr| addr + offset)
+                .ok_or_else(|| AsmError {
+                    line: lineno,
+                    msg: format!("undefined label \"{}\"", name),
+                }),
+        };
+
+        match stmt {
+            Stmt::Isn { op, params } => {
+                let mut isn = op.op;
+                let mut scale = 100;
+                for (mode, _) in &params {
+                    isn += *mode as Word * scale;
+                    scale *= 10;
+                }
+
+                words.push(isn);
+                for (_, expr) in &params {
+                    words.push(resolve(expr)?);
+                }
+            },
+            Stmt::Data(values) => {
+                for expr in &values {
+                    words.push(resolve(expr)?);
+                }
+            },
+        }
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+
+    #[test]
+    fn test_day2_part1_eg0() {
+        let src = "
+            add [9], [10], [3]
+            mul [3], [11], [0]
+            hlt
+            data 30, 40, 50
+        ";
+
+        assert_eq!(
+            assemble(src),
+            Ok(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]));
+    }
+
+    #[test]
+    fn test_day5_part2_eg6() {
+        let src = "
+                in [input]
+                eq [input], #8, [tmp]
+                jnz [tmp], #eight
+                lt #8, [input], [tmp]
+                jz [tmp], #below
+                jz #0, #above
+                data 98
+            tmp:   data 0
+            input: data 0
+            eight:
+                mul [input], #125, [tmp]
+                out [tmp]
+                jnz #1, #done
+            below:
+                out #999
+                jnz #1, #done
+            above:
+                add #1000, #1, [tmp]
+                out [tmp]
+                jnz #1, #done
+                data 98
+            done:
+                hlt
+        ";
+
+        let bytes = [3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+
+        assert_eq!(assemble(src), Ok(bytes.to_vec()));
+    }
+
+    #[test]
+    fn test_day9_relative_base() {
+        let src = "
+            arb #3
+            arb #4
+            out rb-5 ; address 2
+            hlt
+        ";
+
+        assert_eq!(assemble(src), Ok(vec![109,3, 109,4, 204,-5, 99]));
+    }
+
+    #[test]
+    fn test_day9_eg1_quine() {
+        let src = "
+            start:
+                arb #1
+                out rb-1
+                add [100], #1, [100]
+                eq [100], #16, [101]
+                jz [101], #start
+                hlt
+        ";
+
+        assert_eq!(
+            assemble(src),
+            Ok(vec![109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99]));
+    }
+
+    #[test]
+    fn test_label_offsets() {
+        let src = "
+            out [end-1]
+            out #end+2
+            end: hlt
+        ";
+
+        assert_eq!(assemble(src), Ok(vec![4,3, 104,6, 99]));
+    }
+
+    #[test]
+    fn test_disassembly_roundtrip() {
+        let bytes = [3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+
+        let src = disassemble(&bytes, 0).to_string();
+
+        assert_eq!(assemble(&src), Ok(bytes.to_vec()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |line, msg: &str| Err(AsmError { line, msg: msg.into() });
+
+        assert_eq!(
+            assemble("hlt\nfoo #1"),
+            err(2, "unknown mnemonic \"foo\""));
+        assert_eq!(
+            assemble("\nadd #1, #2"),
+            err(2, "add takes 3 operand(s), found 2"));
+        assert_eq!(
+            assemble("in #3"),
+            err(1, "in can't write to an immediate operand"));
+        assert_eq!(
+            assemble("out 3"),
+            err(1, "invalid operand \"3\" (expected [pos], #imm or rb+off)"));
+        assert_eq!(
+            assemble("x: hlt\n\nx: hlt"),
+            err(3, "duplicate label \"x\""));
+        assert_eq!(
+            assemble("hlt\njz #0, #nowhere"),
+            err(2, "undefined label \"nowhere\""));
+    }
+}
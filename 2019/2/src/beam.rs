@@ -0,0 +1,212 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use crate::d2::Coord;
+use crate::fast::FastMachine;
+use crate::lib::{IntcodeError, Word};
+
+// traces a day 19 style tractor beam - a cone out from the origin - one row at a time. both
+// edges only ever move right as we go down, so each row's edges are found by stepping on from
+// the previous row's, rather than probing the whole row. near the origin the beam can be too
+// narrow to cover any point, so rows may be empty
+
+pub trait Probe {
+    fn probe(&mut self, at: Coord) -> Result<bool, IntcodeError>;
+}
+
+// each probe runs a fresh copy of the program
+impl Probe for FastMachine {
+    fn probe(&mut self, Coord { x, y }: Coord) -> Result<bool, IntcodeError> {
+        let mut output = Vec::new();
+        self.clone().try_interpret_async(&mut vec![x as Word, y as Word], &mut output)?;
+
+        Ok(output == [1])
+    }
+}
+
+pub struct Beam<P: Probe> {
+    probe: P,
+    cache: HashMap<Coord, bool>,
+    rows: Vec<Option<(isize, isize)>>, // inclusive
+    runs: usize,
+}
+
+impl<P: Probe> Beam<P> {
+    pub fn new(probe: P) -> Self {
+        Self {
+            probe,
+            cache: HashMap::new(),
+            rows: Vec::new(),
+            runs: 0,
+        }
+    }
+
+    // how many times we've had to probe
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    pub fn get(&mut self, at: Coord) -> Result<bool, IntcodeError> {
+        if let Some(&on) = self.cache.get(&at) {
+            return Ok(on);
+        }
+
+        let on = self.probe.probe(at)?;
+        self.runs += 1;
+        self.cache.insert(at, on);
+
+        Ok(on)
+    }
+
+    // the leftmost and rightmost x covered by the beam in row `y`. the beam starts at the
+    // origin, so there's nothing above it
+    pub fn row(&mut self, y: isize) -> Result<Option<(isize, isize)>, IntcodeError> {
+        if y < 0 {
+            return Ok(None);
+        }
+
+        while self.rows.len() as isize <= y {
+            let row = self.trace(self.rows.len() as isize)?;
+            self.rows.push(row);
+        }
+
+        Ok(self.rows[y as usize])
+    }
+
+    fn trace(&mut self, y: isize) -> Result<Option<(isize, isize)>, IntcodeError> {
+        let (prev_left, prev_right) = self.rows
+            .iter()
+            .rev()
+            .find_map(|&row| row)
+            .unwrap_or((0, 0));
+
+        // give up once we're well past where the last row ended
+        let mut left = prev_left;
+        let limit = prev_right + y + 1;
+        while !self.get(Coord::new(left, y))? {
+            left += 1;
+            if left > limit {
+                return Ok(None);
+            }
+        }
+
+        let mut right = prev_right.max(left);
+        if !self.get(Coord::new(right, y))? {
+            right = left;
+        }
+        while self.get(Coord::new(right + 1, y))? {
+            right += 1;
+        }
+
+        Ok(Some((left, right)))
+    }
+
+    // points covered in the `width` x `height` area at the origin
+    pub fn area(&mut self, width: isize, height: isize) -> Result<usize, IntcodeError> {
+        let mut count = 0;
+
+        for y in 0..height {
+            if let Some((left, right)) = self.row(y)? {
+                let right = right.min(width - 1);
+                if right >= left {
+                    count += (right - left + 1) as usize;
+                }
+            }
+        }
+
+        Ok(count)
+    }
+
+    // top left of the closest `size` x `size` square which fits in the beam, looking down as
+    // far as `max_y`. the square's bottom left is on the beam's left edge, so it fits if the
+    // row at its top reaches far enough right
+    pub fn first_square(&mut self, size: isize, max_y: isize) -> Result<Option<Coord>, IntcodeError> {
+        for bottom in size - 1..=max_y {
+            let top = bottom - (size - 1);
+
+            let left = match self.row(bottom)? {
+                Some((left, _)) => left,
+                None => continue,
+            };
+
+            if let Some((top_left, top_right)) = self.row(top)? {
+                if top_left <= left && top_right >= left + size - 1 {
+                    return Ok(Some(Coord::new(left, top)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // covers x/y between 0.7 and 0.9, so rows 1 to 3 are empty
+    struct Cone;
+
+    impl Cone {
+        fn on(Coord { x, y }: Coord) -> bool {
+            x >= 0 && x * 10 >= y * 7 && x * 10 <= y * 9
+        }
+    }
+
+    impl Probe for Cone {
+        fn probe(&mut self, at: Coord) -> Result<bool, IntcodeError> {
+            Ok(Cone::on(at))
+        }
+    }
+
+    #[test]
+    fn test_rows() {
+        let mut beam = Beam::new(Cone);
+
+        assert_eq!(beam.row(0), Ok(Some((0, 0))));
+        assert_eq!(beam.row(2), Ok(None));
+        assert_eq!(beam.row(4), Ok(Some((3, 3))));
+        assert_eq!(beam.row(100), Ok(Some((70, 90))));
+        assert_eq!(beam.row(-1), Ok(None));
+        assert_eq!(beam.row(isize::MIN), Ok(None));
+
+        // cached
+        let runs = beam.runs();
+        assert_eq!(beam.row(50), Ok(Some((35, 45))));
+        assert_eq!(beam.get(Coord::new(35, 50)), Ok(true));
+        assert_eq!(beam.runs(), runs);
+    }
+
+    #[test]
+    fn test_area() {
+        let brute = (0..50)
+            .flat_map(|y| (0..40).map(move |x| Coord::new(x, y)))
+            .filter(|&c| Cone::on(c))
+            .count();
+
+        let mut beam = Beam::new(Cone);
+        assert_eq!(beam.area(40, 50), Ok(brute));
+        // a handful of probes around each edge, per row
+        assert!(beam.runs() < 50 * 6, "{} runs", beam.runs());
+    }
+
+    #[test]
+    fn test_first_square() {
+        let size = 10;
+        let fits = |c: Coord| {
+            (0..size).all(|dy| (0..size).all(|dx| Cone::on(Coord::new(c.x + dx, c.y + dy))))
+        };
+
+        let mut beam = Beam::new(Cone);
+        let found = beam.first_square(size, 500).unwrap().unwrap();
+        assert!(fits(found));
+
+        // nothing fits in an earlier row
+        for y in 0..found.y {
+            assert!((0..y).all(|x| !fits(Coord::new(x, y))));
+        }
+
+        assert_eq!(Beam::new(Cone).first_square(size, found.y + size - 2), Ok(None));
+    }
+}
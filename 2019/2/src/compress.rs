@@ -0,0 +1,252 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use crate::lib::Word;
+
+// splits a route (turns and forward moves) into a main routine calling up to N movement
+// functions, e.g. day 17's A/B/C, with every line of the encoding kept under a length limit
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Move {
+    Left,
+    Right,
+    Forward(usize),
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Left => write!(f, "L"),
+            Move::Right => write!(f, "R"),
+            Move::Forward(n) => write!(f, "{}", n),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Routine {
+    pub main: Vec<usize>, // indices into `functions`
+    pub functions: Vec<Vec<Move>>,
+}
+
+pub const DEFAULT_MAX_FUNCTIONS: usize = 3;
+pub const DEFAULT_MAX_LEN: usize = 20;
+
+pub struct Compressor {
+    max_functions: usize,
+    max_len: usize,
+}
+
+fn function_name(i: usize) -> char {
+    (b'A' + i as u8) as char
+}
+
+// characters in the comma-separated encoding, not counting the newline
+fn encoded_len(moves: &[Move]) -> usize {
+    let digits = |n: usize| n.to_string().len();
+
+    moves
+        .iter()
+        .map(|m| match m {
+            Move::Forward(n) => digits(*n),
+            _ => 1,
+        })
+        .sum::<usize>()
+        + moves.len().saturating_sub(1)
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|i| i.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Compressor {
+    pub fn new() -> Self {
+        Self {
+            max_functions: DEFAULT_MAX_FUNCTIONS,
+            max_len: DEFAULT_MAX_LEN,
+        }
+    }
+
+    pub fn set_max_functions(&mut self, max: usize) {
+        self.max_functions = max.min(26);
+    }
+
+    pub fn set_max_len(&mut self, max: usize) {
+        self.max_len = max;
+    }
+
+    pub fn compress(&self, route: &[Move]) -> Option<Routine> {
+        let mut routine = Routine {
+            main: Vec::new(),
+            functions: Vec::new(),
+        };
+
+        if self.search(route, &mut routine) {
+            Some(routine)
+        } else {
+            None
+        }
+    }
+
+    // depth first: at each point, try calling an existing function that matches what's left
+    // of the route, then try defining a new one (longest first)
+    fn search(&self, route: &[Move], routine: &mut Routine) -> bool {
+        if route.is_empty() {
+            return true;
+        }
+
+        // "A,B,C" - one more call has to fit
+        if (routine.main.len() + 1) * 2 - 1 > self.max_len {
+            return false;
+        }
+
+        for i in 0..routine.functions.len() {
+            if route.starts_with(&routine.functions[i]) {
+                let len = routine.functions[i].len();
+
+                routine.main.push(i);
+                if self.search(&route[len..], routine) {
+                    return true;
+                }
+                routine.main.pop();
+            }
+        }
+
+        if routine.functions.len() == self.max_functions {
+            return false;
+        }
+
+        let longest = (1..=route.len())
+            .take_while(|&len| encoded_len(&route[..len]) <= self.max_len)
+            .last()
+            .unwrap_or(0);
+
+        for len in (1..=longest).rev() {
+            routine.main.push(routine.functions.len());
+            routine.functions.push(route[..len].to_vec());
+
+            if self.search(&route[len..], routine) {
+                return true;
+            }
+
+            routine.functions.pop();
+            routine.main.pop();
+        }
+
+        false
+    }
+}
+
+impl Routine {
+    pub fn expand(&self) -> Vec<Move> {
+        self.main
+            .iter()
+            .flat_map(|&i| self.functions[i].iter().cloned())
+            .collect()
+    }
+
+    // the ASCII input: main routine, each function, then whether we want the video feed.
+    // functions that are never defined are sent as empty lines
+    pub fn to_input(&self, max_functions: usize, video: bool) -> Vec<Word> {
+        let mut text = self.to_string();
+        for _ in self.functions.len()..max_functions {
+            text.push('\n');
+        }
+        text.push_str(if video { "y\n" } else { "n\n" });
+
+        text.bytes().map(|b| b as Word).collect()
+    }
+}
+
+// one line per routine, each ending in a newline
+impl fmt::Display for Routine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = self.main
+            .iter()
+            .map(|&i| function_name(i))
+            .collect::<Vec<_>>();
+
+        writeln!(f, "{}", join(&names))?;
+        for function in &self.functions {
+            writeln!(f, "{}", join(function))?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn parse_moves(s: &str) -> Option<Vec<Move>> {
+    s.trim()
+        .split(',')
+        .map(|m| match m {
+            "L" => Some(Move::Left),
+            "R" => Some(Move::Right),
+            n => n.parse().ok().map(Move::Forward),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2";
+
+    fn check(compressor: &Compressor, routine: &Routine, route: &[Move]) {
+        assert_eq!(routine.expand(), route);
+        assert!(routine.functions.len() <= compressor.max_functions);
+
+        for line in routine.to_string().lines() {
+            assert!(line.len() <= compressor.max_len, "{:?} too long", line);
+        }
+    }
+
+    #[test]
+    fn test_sample() {
+        let route = parse_moves(SAMPLE).unwrap();
+        let compressor = Compressor::new();
+
+        let routine = compressor.compress(&route).unwrap();
+        check(&compressor, &routine, &route);
+        assert_eq!(routine.functions.len(), 3);
+
+        let input = routine.to_input(3, false);
+        let text = input.iter().map(|&w| w as u8 as char).collect::<String>();
+        assert_eq!(text.lines().count(), 5);
+        assert!(text.ends_with("\nn\n"));
+    }
+
+    #[test]
+    fn test_limits() {
+        let route = parse_moves(SAMPLE).unwrap();
+
+        let mut compressor = Compressor::new();
+        compressor.set_max_functions(1);
+        assert_eq!(compressor.compress(&route), None);
+
+        compressor.set_max_functions(2);
+        compressor.set_max_len(8);
+        assert_eq!(compressor.compress(&route), None);
+
+        // short enough to leave functions unused
+        compressor.set_max_len(20);
+        let route = parse_moves("R,8,R,8").unwrap();
+        let routine = compressor.compress(&route).unwrap();
+        check(&compressor, &routine, &route);
+        assert_eq!(routine.to_string(), "A\nR,8,R,8\n");
+
+        let text = routine.to_input(3, true).iter().map(|&w| w as u8 as char).collect::<String>();
+        assert_eq!(text, "A\nR,8,R,8\n\n\ny\n");
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        assert_eq!(encoded_len(&parse_moves("L,10,R,6").unwrap()), 8);
+        assert_eq!(encoded_len(&[]), 0);
+    }
+}
@@ -14,7 +14,7 @@ impl Coord {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
 pub enum Compass {
     North,
     South,
@@ -22,6 +22,19 @@ pub enum Compass {
     East,
 }
 
+impl Compass {
+    pub const ALL: [Compass; 4] = [Compass::North, Compass::South, Compass::West, Compass::East];
+
+    pub fn opposite(self) -> Self {
+        match self {
+            Compass::North => Compass::South,
+            Compass::South => Compass::North,
+            Compass::West => Compass::East,
+            Compass::East => Compass::West,
+        }
+    }
+}
+
 impl std::ops::Add<Compass> for Coord {
     type Output = Self;
     fn add(mut self, rhs: Compass) -> Self {
@@ -75,7 +75,15 @@ mod parse;
 
 mod lib;
 use lib::{IntCodeMachine, Word, State as ICMState};
-use std::collections::HashMap;
+
+mod d2;
+use d2::Coord;
+
+mod grid;
+use grid::Grid;
+
+mod render;
+use render::{Glyph, Renderer};
 
 #[derive(Clone, Copy, Debug)]
 enum Colour {
@@ -100,18 +108,6 @@ impl Colour {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
-struct Coord {
-    x: isize,
-    y: isize,
-}
-
-impl Coord {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
-    }
-}
-
 impl std::ops::AddAssign<Dir> for Coord {
     fn add_assign(&mut self, rhs: Dir) {
         *self = match rhs {
@@ -175,7 +171,23 @@ enum RobotState {
     Done,
 }
 
-type PaintGrid = HashMap<Coord, Colour>;
+impl Glyph for Colour {
+    fn glyph(&self) -> char {
+        match self {
+            Colour::Black => '.',
+            Colour::White => '#',
+        }
+    }
+
+    fn colour(&self) -> Option<render::Colour> {
+        match self {
+            Colour::Black => None,
+            Colour::White => Some(render::Colour::Green),
+        }
+    }
+}
+
+type PaintGrid = Grid<Colour>;
 
 struct Robot {
     brain: IntCodeMachine,
@@ -197,10 +209,7 @@ impl Robot {
             self.start = false;
             Colour::White
         } else {
-            match self.painted.get(&self.location) {
-                Some(&c) => c,
-                None => Colour::Black,
-            }
+            *self.painted.get_default(&self.location)
         };
 
         //println!("current colour: {:?}", colour);
@@ -218,7 +227,7 @@ impl Robot {
     }
 
     fn paint(&mut self, new_colour: Colour) {
-        self.painted.insert(self.location, new_colour);
+        self.painted.map.insert(self.location, new_colour);
     }
 
     fn turn_and_move(&mut self, turn: Turn) {
@@ -227,33 +236,10 @@ impl Robot {
     }
 }
 
-fn show_paint(painted: &PaintGrid) {
-    let min = Coord {
-        x: painted.keys().min_by(|a, b| a.x.cmp(&b.x)).unwrap().x,
-        y: painted.keys().min_by(|a, b| a.y.cmp(&b.y)).unwrap().y,
-    };
-    let max = Coord {
-        x: painted.keys().max_by(|a, b| a.x.cmp(&b.x)).unwrap().x,
-        y: painted.keys().max_by(|a, b| a.y.cmp(&b.y)).unwrap().y,
-    };
-
-    println!("painted, min: {:?}, max: {:?}", min, max);
-    for y in min.y ..= max.y {
-        for x in min.x ..= max.x {
-            let colour = painted
-                   .get(&Coord { x, y })
-                   .map(|&c| c)
-                   .unwrap_or(Colour::Black);
-
-            let s = match colour {
-                Colour::Black => ".",
-                Colour::White => "\x1b[32m#\x1b[0m",
-            };
+fn show_paint(painted: &PaintGrid) -> std::io::Result<()> {
+    let (min, max) = painted.minmax();
 
-            print!("{}", s);
-        }
-        println!();
-    }
+    Renderer::stdout().draw(painted, &format!("painted, min: {:?}, max: {:?}", min, max))
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -261,7 +247,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut robot = Robot {
         brain: IntCodeMachine::new(&bytes, false),
-        painted: Default::default(),
+        painted: Grid::new_default(Colour::Black),
 
         location: Coord::new(0, 0),
         facing: Dir::Up,
@@ -273,7 +259,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     //println!("{} {}", robot.painted.keys().count());
 
-    show_paint(&robot.painted);
+    show_paint(&robot.painted)?;
 
     Ok(())
 }
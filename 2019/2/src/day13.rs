@@ -33,35 +33,36 @@
 mod parse;
 use parse::bytes;
 
-use std::io::{self, Write, Read};
-use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
 use std::process::Command;
 
 mod lib;
-use lib::{IntCodeMachine, Word, State as ICMState};
+use lib::{IntCodeMachine, Snapshot, Word, State as ICMState};
 
-const ESC_UP: &str = "\x1b[A";
+mod d2;
+use d2::Coord;
 
-type Screen = HashMap<Coord, Tile>;
+mod grid;
+use grid::Grid;
+
+mod render;
+use render::{apply_triples, Colour, Glyph, Renderer};
+
+type Screen = Grid<Tile>;
+
+const RECORDING: &str = "./joystick-day13";
 
 struct Game {
     machine: IntCodeMachine,
-    save: (Vec<Word>, Screen),
+    save: Option<(Snapshot, Screen, Word, usize)>,
     screen: Screen,
     score: Word,
+    moves: Vec<Joystick>, // every input given, for replaying
 
-    min_x: Word,
-    max_x: Word,
-    min_y: Word,
-    max_y: Word,
-
-    printed: bool,
-}
-
-#[derive(PartialEq, Eq, Debug, Hash, Clone)]
-struct Coord {
-    x: Word,
-    y: Word,
+    renderer: Renderer<io::Stdout>,
 }
 
 #[derive(PartialEq, Eq, Clone)]
@@ -73,7 +74,7 @@ enum Tile {
     Ball,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 enum Joystick {
     Neutral,
     Left,
@@ -88,6 +89,15 @@ impl Joystick {
             Joystick::Right => 1,
         }
     }
+
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            'x' => Some(Joystick::Neutral),
+            'h' => Some(Joystick::Left),
+            'l' => Some(Joystick::Right),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Joystick {
@@ -118,8 +128,63 @@ impl Tile {
     }
 }
 
-fn save_input(j: Joystick) {
-    eprintln!("{}", j);
+impl Glyph for Tile {
+    fn glyph(&self) -> char {
+        match self {
+            Tile::Empty => ' ',
+            Tile::Wall => '|',
+            Tile::Block => '#',
+            Tile::HorizPaddle => '_',
+            Tile::Ball => 'o',
+        }
+    }
+
+    fn colour(&self) -> Option<Colour> {
+        match self {
+            Tile::Block => Some(Colour::Blue),
+            Tile::Ball => Some(Colour::Yellow),
+            _ => None,
+        }
+    }
+}
+
+// a "score N" header, then one move per line in the same keys `interact` takes
+fn write_recording(path: &str, score: Word, moves: &[Joystick]) -> io::Result<()> {
+    fs::write(path, recording(score, moves))
+}
+
+fn recording(score: Word, moves: &[Joystick]) -> String {
+    let moves = moves
+        .iter()
+        .map(|j| format!("{}\n", j))
+        .collect::<String>();
+
+    format!("score {}\n{}", score, moves)
+}
+
+// the score the recording should get, and its moves
+fn parse_recording(s: &str) -> Result<(Word, Vec<Joystick>), String> {
+    let mut lines = s.lines();
+
+    let score = lines
+        .next()
+        .and_then(|header| header.strip_prefix("score "))
+        .and_then(|score| score.parse().ok())
+        .ok_or_else(|| "line 1: expected a \"score N\" header".to_string())?;
+
+    let moves = lines
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            let mut chars = line.chars();
+            match (chars.next().and_then(Joystick::from_char), chars.next()) {
+                (Some(j), None) => Ok(j),
+                _ => Err(format!("line {}: invalid move {:?}", i + 2, line)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok((score, moves))
 }
 
 impl Game {
@@ -128,23 +193,19 @@ impl Game {
 
         Game {
             machine,
-            screen: HashMap::new(),
-            save: (Vec::new(), HashMap::new()),
+            screen: Grid::new_default(Tile::Empty),
+            save: None,
 
             score: 0,
+            moves: Vec::new(),
 
-            min_x: 0,
-            max_x: 0,
-            min_y: 0,
-            max_y: 0,
-
-            printed: false,
+            renderer: Renderer::stdout(),
         }
     }
 
     fn is_active(&self) -> bool {
         match self.machine.state() {
-            ICMState::Running => true,
+            ICMState::Running | ICMState::AwaitingInput => true,
             ICMState::Halted => false,
         }
     }
@@ -154,37 +215,67 @@ impl Game {
 
         assert_eq!(outputs.len() % 3, 0);
 
-        //println!("outputs: {}", outputs.len());
+        let scores = apply_triples(&mut self.screen, &outputs, |at, tile| {
+            if at == Coord::new(-1, 0) {
+                None
+            } else {
+                Some(Tile::from(tile))
+            }
+        });
 
-        for i in (0..outputs.len()).step_by(3) {
-            let slice = (
-                outputs[i],
-                outputs[i+1],
-                outputs[i+2],
-            );
+        if let Some(&(_, score)) = scores.last() {
+            self.score = score;
+        }
+    }
 
-            //println!("output slice: {:?}", slice);
+    fn ball_and_paddle(&self) -> Option<(Coord, Coord)> {
+        let find = |tile| self.screen.map
+            .iter()
+            .find(|&(_, t)| t == &tile)
+            .map(|(&c, _)| c);
 
-            let at = Coord {
-                x: slice.0,
-                y: slice.1,
-            };
+        Some((find(Tile::Ball)?, find(Tile::HorizPaddle)?))
+    }
 
-            if at.x == -1 && at.y == 0 {
-                self.score = slice.2;
-                continue;
+    // keep the paddle under the ball
+    fn autopilot(&self) -> Joystick {
+        match self.ball_and_paddle() {
+            Some((ball, paddle)) if ball.x < paddle.x => Joystick::Left,
+            Some((ball, paddle)) if ball.x > paddle.x => Joystick::Right,
+            _ => Joystick::Neutral,
+        }
+    }
+
+    // runs the game to the end, asking `pilot` for each move. fails if it gives up first
+    fn play<F>(&mut self, mut pilot: F, show: bool) -> Result<Word, String>
+        where F: FnMut(&Game) -> Option<Joystick>
+    {
+        let mut inputs = Vec::new();
+
+        loop {
+            self.run(&mut inputs);
+            if show {
+                self.show();
             }
 
-            let tile = Tile::from(slice.2);
+            if !self.is_active() {
+                return Ok(self.score);
+            }
 
-            self.min_x = self.min_x.min(at.x);
-            self.max_x = self.max_x.max(at.x);
-            self.min_y = self.min_y.min(at.y);
-            self.max_y = self.max_y.max(at.y);
-            self.screen.insert(at, tile);
+            let j = pilot(self).ok_or_else(|| format!("ran out of moves after {}", self.moves.len()))?;
+            inputs.push(j.to());
+            self.moves.push(j);
         }
     }
 
+    fn blocks(&self) -> usize {
+        self.screen
+            .map
+            .values()
+            .filter(|&v| v == &Tile::Block)
+            .count()
+    }
+
     fn run_til_end(&mut self) {
         while self.is_active() {
             self.run(&mut vec![])
@@ -192,41 +283,22 @@ impl Game {
     }
 
     fn show(&mut self) {
-        if self.printed {
-            for _ in self.min_y..=self.max_y+1 {
-                print!("{}", ESC_UP);
-            }
-        }
-
-        println!("score {} {}\x1b[0K", self.score, if self.save.0.len() > 0 { "save present" } else { "" });
-        for y in self.min_y..=self.max_y {
-            for x in self.min_x..=self.max_x {
-                let tile = self.screen.get(&Coord { x, y }).unwrap_or(&Tile::Empty);
-
-                let c = match tile {
-                    Tile::Empty => ' ',
-                    Tile::Wall => '|',
-                    Tile::Block => '#',
-                    Tile::HorizPaddle => '_',
-                    Tile::Ball => 'o',
-                };
+        let status = format!("score {} {}", self.score, if self.save.is_some() { "save present" } else { "" });
 
-                print!("{}", c);
-            }
-            println!();
-        }
-        self.printed = true;
+        self.renderer.draw(&self.screen, &status).unwrap();
     }
 
     fn save(&mut self) {
-        self.save.0.clear();
-        self.save.0.extend_from_slice(self.machine.memory());
-        self.save.1 = self.screen.clone();
+        self.save = Some((self.machine.snapshot(), self.screen.clone(), self.score, self.moves.len()));
     }
 
     fn load(&mut self) {
-        self.machine.load_memory(&self.save.0);
-        self.screen = self.save.1.clone();
+        if let Some((snapshot, screen, score, moves)) = &self.save {
+            self.machine.restore(snapshot);
+            self.screen = screen.clone();
+            self.score = *score;
+            self.moves.truncate(*moves);
+        }
     }
 
     fn interact(&mut self) {
@@ -234,7 +306,6 @@ impl Game {
         stty.arg("-echo").arg("-icanon");
         stty.status().expect("stty failed");
 
-        let stdout = io::stdout();
         let mut reader = io::stdin();
         let mut buffer = [0; 1]; // read exactly one byte
 
@@ -245,7 +316,6 @@ impl Game {
 
                 let j = loop {
                     self.show();
-                    stdout.lock().flush().unwrap();
                     reader.read_exact(&mut buffer).unwrap();
 
                     match buffer[0] as char {
@@ -261,10 +331,10 @@ impl Game {
                     };
                 };
                 inputs.push(j.to());
-                save_input(j);
+                self.moves.push(j);
             }
 
-            if self.save.0.len() > 0 {
+            if self.save.is_some() {
                 self.load();
             } else {
                 break;
@@ -279,25 +349,114 @@ fn part1(bytes: &mut [Word]) {
 
     game.run_til_end();
 
-    println!("{}",
-        game.screen
-            .values()
-            .filter(|&v| v == &Tile::Block)
-            .count());
+    println!("{}", game.blocks());
 }
 
+fn replay(bytes: &[Word], moves: &[Joystick]) -> Result<Word, String> {
+    let mut game = Game::new(bytes);
+    let mut moves = moves.iter();
+
+    game.play(|_| moves.next().cloned(), false)
+}
+
+// autoplays (or with `play`, lets you play) and records the moves, unless there's already a
+// recording, in which case that's replayed. either way, the recording is replayed to check
+// it gets the score it was recorded with
 #[allow(dead_code)]
-fn part2(bytes: &mut [Word]) {
+fn part2(bytes: &mut [Word]) -> Result<(), Box<dyn std::error::Error>> {
     bytes[0] = 2; // play for free
+
+    if Path::new(RECORDING).exists() {
+        let (expected, moves) = parse_recording(&fs::read_to_string(RECORDING)?)?;
+        let replayed = replay(bytes, &moves)?;
+        if replayed != expected {
+            return Err(format!("{} scored {}, expected {}", RECORDING, replayed, expected).into());
+        }
+
+        println!("replayed: {}", replayed);
+        return Ok(());
+    }
+
     let mut game = Game::new(bytes);
-    game.interact();
+    if env::args().nth(1).as_deref() == Some("play") {
+        game.interact();
+    } else {
+        game.play(|g| Some(g.autopilot()), false)?;
+    }
+
+    // the game ended without winning, so there's nothing worth keeping
+    if game.blocks() > 0 {
+        return Err(format!("game over with {} blocks left, score {}", game.blocks(), game.score).into());
+    }
+
+    write_recording(RECORDING, game.score, &game.moves)?;
+
+    let (expected, moves) = parse_recording(&fs::read_to_string(RECORDING)?)?;
+    let replayed = replay(bytes, &moves)?;
+    if replayed != expected {
+        return Err(format!("replay scored {}, expected {}", replayed, expected).into());
+    }
+
+    println!("score: {}", game.score);
+
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let bytes = bytes("./input-day13")?;
 
     part1(&mut bytes.clone());
-    part2(&mut bytes.clone());
+    part2(&mut bytes.clone())?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_roundtrip() {
+        let moves = [Joystick::Neutral, Joystick::Left, Joystick::Right, Joystick::Right];
+
+        let text = recording(12952, &moves);
+        assert_eq!(text, "score 12952\nx\nh\nl\nl\n");
+        assert_eq!(parse_recording(&text), Ok((12952, moves.to_vec())));
+
+        assert_eq!(parse_recording("score 7\nx\nq\n"), Err("line 3: invalid move \"q\"".into()));
+        assert_eq!(parse_recording("x\nh\n"), Err("line 1: expected a \"score N\" header".into()));
+        assert_eq!(parse_recording(""), Err("line 1: expected a \"score N\" header".into()));
+    }
+
+    #[test]
+    fn test_autopilot() {
+        let mut game = Game::new(&[99]);
+        assert_eq!(game.autopilot(), Joystick::Neutral);
+
+        game.screen.map.insert(Coord::new(3, 5), Tile::HorizPaddle);
+        game.screen.map.insert(Coord::new(1, 2), Tile::Ball);
+        assert_eq!(game.autopilot(), Joystick::Left);
+
+        game.screen.map.insert(Coord::new(1, 2), Tile::Empty);
+        game.screen.map.insert(Coord::new(3, 4), Tile::Ball);
+        assert_eq!(game.autopilot(), Joystick::Neutral);
+
+        game.screen.map.insert(Coord::new(9, 1), Tile::Ball);
+        game.screen.map.insert(Coord::new(3, 4), Tile::Empty);
+        assert_eq!(game.autopilot(), Joystick::Right);
+    }
+
+    #[test]
+    fn test_replay_runs_out() {
+        // reads one joystick position, outputs the score, then reads another
+        let prog = [3,13,104,-1,104,0,4,13,3,13,99];
+        let mut game = Game::new(&prog);
+
+        let moves = [Joystick::Right];
+        let mut it = moves.iter();
+        assert_eq!(game.play(|_| it.next().cloned(), false), Err("ran out of moves after 1".into()));
+        assert_eq!(game.score, 1);
+
+        assert_eq!(replay(&prog, &[Joystick::Left, Joystick::Neutral]), Ok(-1));
+    }
+}
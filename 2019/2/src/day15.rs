@@ -1,4 +1,5 @@
 #![allow(unused_imports)]
+use std::env;
 use std::fs;
 
 use std::io::{self, Write, Read};
@@ -6,164 +7,64 @@ use std::process::Command;
 
 mod lib;
 use lib::{IntCodeMachine, Word, State as ICMState};
-use std::collections::HashMap;
 
-mod parse;
-use parse::bytes;
-
-use std::convert::TryInto;
+mod d2;
+use d2::{Compass, Coord};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
-struct Coord {
-    x: isize,
-    y: isize,
-}
+mod grid;
+use grid::Grid;
 
-impl Coord {
-    fn new(x: isize, y: isize) -> Self {
-        Self { x, y }
-    }
-}
+mod render;
+use render::{Colour, Glyph, Renderer};
 
-//impl std::cmp::Ord for Coord {
-//}
+mod maze;
+use maze::GridEnt;
 
-impl std::ops::AddAssign<Dir> for Coord {
-    fn add_assign(&mut self, rhs: Dir) {
-        *self = match rhs {
-            Dir::North => Coord::new(self.x, self.y - 1),
-            Dir::South => Coord::new(self.x, self.y + 1),
-            Dir::West => Coord::new(self.x - 1, self.y),
-            Dir::East => Coord::new(self.x + 1, self.y),
-        };
-    }
-}
-
-impl std::ops::Add<Dir> for Coord {
-    type Output = Self;
-    fn add(mut self, rhs: Dir) -> Self {
-        self += rhs;
-        self
-    }
-}
-
-/*
-#[derive(Clone, Copy)]
-enum Turn {
-    Left,
-    Right,
-}
+mod parse;
+use parse::bytes;
 
-impl std::ops::AddAssign<Turn> for Dir {
-    fn add_assign(&mut self, rhs: Turn) {
-        *self = match self {
-            Dir::North => match rhs {
-                Turn::Left => Dir::West,
-                Turn::Right => Dir::East,
-            },
-            Dir::West => match rhs {
-                Turn::Left => Dir::South,
-                Turn::Right => Dir::North,
-            },
-            Dir::South => match rhs {
-                Turn::Left => Dir::East,
-                Turn::Right => Dir::West,
-            },
-            Dir::East => match rhs {
-                Turn::Left => Dir::North,
-                Turn::Right => Dir::South,
-            },
-        }
-    }
-}
-*/
-
-#[derive(Clone, Copy)]
-enum Dir {
-    North,
-    South,
-    West,
-    East,
-}
+use std::convert::TryInto;
 
-impl From<Dir> for i64 {
-    fn from(dir: Dir) -> i64 {
-        match dir {
-            Dir::North => 1,
-            Dir::South => 2,
-            Dir::West => 3,
-            Dir::East => 4,
+impl Glyph for GridEnt {
+    fn glyph(&self) -> char {
+        match self {
+            GridEnt::Wall => '#',
+            GridEnt::Ok => ' ',
+            GridEnt::Found => '@',
+            GridEnt::Unknown => '?',
+            GridEnt::Droid => 'o',
         }
     }
-}
 
-#[derive(Clone, Copy, Debug)]
-enum GridEnt {
-    Wall,
-    Ok,
-    Found,
-    Unknown,
-}
-
-impl From<i64> for GridEnt {
-    fn from(e: i64) -> GridEnt {
-        match e {
-            0 => GridEnt::Wall,
-            1 => GridEnt::Ok,
-            2 => GridEnt::Found,
-            _ => panic!("can't convert {} to grident", e),
+    fn colour(&self) -> Option<Colour> {
+        match self {
+            GridEnt::Wall => Some(Colour::Green),
+            GridEnt::Ok => None,
+            GridEnt::Found => Some(Colour::White),
+            GridEnt::Unknown => Some(Colour::Red),
+            GridEnt::Droid => Some(Colour::Yellow),
         }
     }
 }
 
-type Grid = HashMap<Coord, GridEnt>;
-
 struct Robot {
     machine: IntCodeMachine,
 
-    grid: Grid,
+    grid: Grid<GridEnt>,
+    renderer: Renderer<io::Stdout>,
 
     coord: Coord,
 }
 
 impl Robot {
-    fn draw(&self) {
-        let mut min_x = 0;
-        let mut min_y = 0;
-        let mut max_x = 0;
-        let mut max_y = 0;
-
-        for coord in self.grid.keys() {
-            min_x = min_x.min(coord.x);
-            max_x = max_x.max(coord.x);
-            min_y = min_y.min(coord.y);
-            max_y = max_y.max(coord.y);
-        }
+    fn draw(&mut self) {
+        let mut grid = self.grid.clone();
+        grid.map.insert(self.coord, GridEnt::Droid);
 
-        println!(
-            "------- min = {:?}, max= {:?}",
-            Coord { x: min_x, y: min_y },
-            Coord { x: max_x, y: max_y });
-
-        for y in min_y..=max_y {
-            for x in min_x..=max_x {
-                let ent = *self.grid.get(&Coord {x,y}).unwrap_or(&GridEnt::Unknown);
-
-                let ch = if (Coord { x, y }) == self.coord {
-                    "\x1b[33mo\x1b[0m"
-                } else {
-                    match ent {
-                        GridEnt::Wall => "\x1b[32m#\x1b[0m",
-                        GridEnt::Ok => " ",
-                        GridEnt::Found => "\x1b[40m@\x10[0m",
-                        GridEnt::Unknown => "\x1b[31m?\x1b[0m",
-                    }
-                };
+        let (min, max) = grid.minmax();
+        let status = format!("------- min = {:?}, max= {:?}", min, max);
 
-                print!("{}",  ch);
-            }
-            print!("\n");
-        }
+        self.renderer.draw(&grid, &status).unwrap();
     }
 
     fn run(&mut self) {
@@ -182,21 +83,21 @@ impl Robot {
                 reader.read_exact(&mut buffer).unwrap();
 
                 match buffer[0] as char {
-                    'h' => break Dir::West,
-                    'l' => break Dir::East,
-                    'k' => break Dir::North,
-                    'j' => break Dir::South,
+                    'h' => break Compass::West,
+                    'l' => break Compass::East,
+                    'k' => break Compass::North,
+                    'j' => break Compass::South,
                     _ => {
                         eprintln!("invalid input");
                     },
                 };
             };
 
-            let output = self.machine.interpret_async(&mut vec![dir.into()]);
+            let output = self.machine.interpret_async(&mut vec![maze::command(dir)]);
             assert_eq!(output.len(), 1);
             let answer: GridEnt = output[0].into();
 
-            self.grid.insert(
+            self.grid.map.insert(
                 self.coord + dir,
                 answer);
 
@@ -210,52 +111,11 @@ impl Robot {
                     self.coord += dir;
                     break;
                 },
-                GridEnt::Unknown => panic!(),
+                GridEnt::Unknown | GridEnt::Droid => panic!(),
             }
         };
         println!("found at {:?}", self.coord);
     }
-
-    /*
-    fn auto(&mut self) {
-        let dir = Dir::North;
-        //let mut moveback = false;
-
-        loop {
-            let output = self.machine.interpret_async(&mut vec![dir.into()]);
-            assert_eq!(output.len(), 1);
-            let answer: GridEnt = output[0].into();
-
-            self.grid.insert(
-                self.coord + dir,
-                answer);
-
-            match answer {
-                GridEnt::Wall => {
-                    /*
-                    if moveback {
-                        moveback = false;
-                        dir = -dir;
-                    } else {
-
-                    }
-                    TODO
-                    */
-                },
-                GridEnt::Ok => {
-                    self.coord += dir;
-                },
-                GridEnt::Found => {
-                    self.coord += dir;
-                    break;
-                },
-                GridEnt::Unknown => panic!(),
-            }
-        };
-
-        println!("found at {:?}", self.coord);
-    }
-    */
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -263,7 +123,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut robot = Robot {
         machine: IntCodeMachine::new(&bytes, false),
-        grid: Default::default(),
+        grid: Grid::new_default(GridEnt::Unknown),
+        renderer: Renderer::stdout(),
         coord: Coord { x: 0, y: 0 },
     };
 
@@ -302,7 +163,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     */
 
-    robot.run();
+    if env::args().nth(1).as_deref() == Some("play") {
+        robot.run();
+        return Ok(());
+    }
+
+    robot.grid = maze::explore(&mut robot.machine)?;
+    robot.draw();
+
+    let oxygen = maze::find(&robot.grid, GridEnt::Found).ok_or("no oxygen system")?;
+    let path = maze::shortest_path(&robot.grid, robot.coord, oxygen).ok_or("can't reach oxygen system")?;
+    println!("oxygen system at {:?}, {} moves away", oxygen, path);
+
+    let minutes = maze::oxygen_fill(&robot.grid, oxygen, |_, _| {});
+    println!("filled after {} minutes", minutes);
 
     Ok(())
 }
@@ -5,6 +5,9 @@ use parse::bytes;
 
 mod lib;
 use lib::{IntCodeMachine, Word};
+
+mod compress;
+use compress::{Compressor, Move, DEFAULT_MAX_FUNCTIONS};
 use std::collections::{HashMap/*, HashSet*/};
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
@@ -21,6 +24,30 @@ impl Coord {
     fn zero() -> Self {
         Self::new(0, 0)
     }
+
+    fn step(self, dir: Dir) -> Self {
+        match dir {
+            Dir::North => Self::new(self.x, self.y - 1),
+            Dir::South => Self::new(self.x, self.y + 1),
+            Dir::West => Self::new(self.x - 1, self.y),
+            Dir::East => Self::new(self.x + 1, self.y),
+        }
+    }
+}
+
+impl Dir {
+    fn left(self) -> Self {
+        match self {
+            Dir::North => Dir::West,
+            Dir::West => Dir::South,
+            Dir::South => Dir::East,
+            Dir::East => Dir::North,
+        }
+    }
+
+    fn right(self) -> Self {
+        self.left().left().left()
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -168,6 +195,43 @@ impl View {
         overlaps
     }
 
+    fn robot(&self) -> Option<(Coord, Dir)> {
+        self.map.iter().find_map(|(&coord, tile)| match tile {
+            Tile::Robot(dir) => Some((coord, *dir)),
+            _ => None,
+        })
+    }
+
+    // go straight over every crossing, turning only at corners. this covers the whole
+    // scaffold as long as it's a single path with no dead ends other than the far end
+    fn route(&self) -> Vec<Move> {
+        let (mut pos, mut dir) = self.robot().expect("couldn't find robot");
+        let mut route = Vec::new();
+
+        loop {
+            let mut forward = 0;
+            while self.is_scaffold(&pos.step(dir)) {
+                pos = pos.step(dir);
+                forward += 1;
+            }
+            if forward > 0 {
+                route.push(Move::Forward(forward));
+            }
+
+            if self.is_scaffold(&pos.step(dir.left())) {
+                dir = dir.left();
+                route.push(Move::Left);
+            } else if self.is_scaffold(&pos.step(dir.right())) {
+                dir = dir.right();
+                route.push(Move::Right);
+            } else {
+                break;
+            }
+        }
+
+        route
+    }
+
     /*
     fn walk_direction(
         &self,
@@ -301,48 +365,22 @@ fn part2() -> Result<(), Box<dyn std::error::Error>> {
     let mut bytes = bytes("./input-day17")?;
 
     assert_eq!(bytes[0], 1);
+    let view = View::parse(IntCodeMachine::new(&bytes, false).interpret_async(&mut vec![]));
     bytes[0] = 2;
 
-    let mut machine = IntCodeMachine::new(&bytes, false);
-
-    let mut input = Vec::new();
-
-    input.extend_from_slice(&[
-        'A' as Word,
-        ',' as Word,
-        'B' as Word,
-        ',' as Word,
-        'A' as Word,
-        ',' as Word,
-        'B' as Word,
-        ',' as Word,
-        'C' as Word,
-        ',' as Word,
-        'C' as Word,
-        ',' as Word,
-        'B' as Word,
-        ',' as Word,
-        'A' as Word,
-        ',' as Word,
-        'B' as Word,
-        ',' as Word,
-        'C' as Word,
-        '\n' as Word,
-    ]);
-    input.extend_from_slice(&[
-        'L' as Word, ',' as Word, '8' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '2' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '2' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '0' as Word, '\n' as Word,
-        'R' as Word, ',' as Word, '1' as Word, '0' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '2' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '0' as Word, '\n' as Word,
-        'L' as Word, ',' as Word, '1' as Word, '0' as Word, ',' as Word, 'R' as Word, ',' as Word, '1' as Word, '0' as Word, ',' as Word, 'L' as Word, ',' as Word, '6' as Word, '\n' as Word,
-        'n' as Word, '\n' as Word,
-    ]);
+    let routine = Compressor::new()
+        .compress(&view.route())
+        .ok_or("couldn't compress route")?;
 
+    let mut machine = IntCodeMachine::new(&bytes, false);
+    let mut input = routine.to_input(DEFAULT_MAX_FUNCTIONS, false);
     let output = machine.interpret_async(&mut input);
 
     //let view = View::parse(output);
 
     //let routes = view.possible_routes();
 
-    println!("{:?}", output);
+    println!("{:?}", output.last());
 
     Ok(())
 }
@@ -353,3 +391,40 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+#######...#####
+#.....#...#...#
+#.....#...#...#
+......#...#...#
+......#...###.#
+......#.....#.#
+^########...#.#
+......#.#...#.#
+......#########
+........#...#..
+....#########..
+....#...#......
+....#...#......
+....#...#......
+....#####......
+";
+
+    #[test]
+    fn test_sample_route() {
+        let view = View::parse(SAMPLE.bytes().map(|b| b as Word).collect());
+
+        let route = view.route();
+        assert_eq!(
+            route,
+            compress::parse_moves("R,8,R,8,R,4,R,4,R,8,L,6,L,2,R,4,R,4,R,8,R,8,R,8,L,6,L,2").unwrap());
+
+        let routine = Compressor::new().compress(&route).unwrap();
+        assert_eq!(routine.expand(), route);
+        assert!(routine.to_string().lines().all(|line| line.len() <= 20));
+    }
+}
@@ -1,12 +1,15 @@
+use std::fs;
 use std::io;
 
 mod parse;
 mod lib;
 mod ascii;
 
-use itertools::Itertools;
 use ascii::AsciiMachine;
 
+mod explorer;
+use explorer::Explorer;
+
 fn line() -> String {
     let mut line = String::new();
 
@@ -21,102 +24,47 @@ fn line() -> String {
     }
 }
 
-fn part1() -> Result<(), Box<dyn std::error::Error>> {
-    let initial_steps = [
-        "north",
-        "west",
-        "west",
-        "west",
-        "west",
-        "east",
-        "south",
-        "north",
-        "west",
-        "take tambourine",
-        "east",
-        "west",
-        "east",
-        "east",
-        "take candy cane",
-        "east",
-        "east",
-        "east",
-        "take ornament",
-        "north",
-        "north",
-        "take dark matter",
-        "south",
-        "south",
-        "west",
-        "south",
-        "south",
-        "east",
-        "take whirled peas",
-        "west",
-        "north",
-        "north",
-        "west",
-        "north",
-        "take astrolabe",
-        "east",
-        "take hologram",
-        "east",
-        "take klein bottle",
-        "west",
-        "south",
-        "west",
-        "north",
-    ];
+const TRANSCRIPT: &str = "./transcript-day25";
+const CHECKPOINT: &str = "./checkpoint-day25";
+const CHECKPOINT_STATE: &str = "./checkpoint-day25.explorer";
+
+// explores up to the security checkpoint and saves there, or resumes from a previous save
+fn explore(bytes: &[lib::Word]) -> Result<Explorer<AsciiMachine>, Box<dyn std::error::Error>> {
+    if let (Ok(machine), Ok(state)) = (AsciiMachine::load(CHECKPOINT), fs::read_to_string(CHECKPOINT_STATE)) {
+        eprintln!("resuming from {}", CHECKPOINT);
+        return Ok(Explorer::resume(machine, &state)?);
+    }
+
+    let mut explorer = Explorer::new(AsciiMachine::new(bytes));
+    explorer.explore()?;
+
+    explorer.game().save(CHECKPOINT)?;
+    fs::write(CHECKPOINT_STATE, explorer.state())?;
+
+    Ok(explorer)
+}
 
+fn part1() -> Result<(), Box<dyn std::error::Error>> {
     let bytes = parse::bytes("./input-day25")?;
 
-    let mut machine = AsciiMachine::new(&bytes);
-    let mut input = initial_steps
-        .join("\n");
-    input.push('\n');
-
-    let _out = machine.run(input);
-
-    let inventory = vec![
-        "ornament",
-        "klein bottle",
-        "dark matter",
-        "candy cane",
-        "hologram",
-        "astrolabe",
-        "whirled peas",
-        "tambourine",
-    ];
-
-    let mut can_continue = vec![];
-
-    for n_to_drop in 1..inventory.len()-1 {
-        for inv in inventory.iter().combinations(n_to_drop) {
-            let mut machine = machine.clone();
-
-            let mut input = inv
-                .iter()
-                .map(|&item| String::from("drop ") + item + "\n")
-                .collect::<Vec<_>>();
-
-            input.push("north\n".into());
-            let input = input.join("");
-
-            let output = machine.run(input);
-
-            let success = !output.contains("ejected back to the checkpoint");
-            if success {
-                println!("permutation {:?}: {} {}", inv, success, output);
-                can_continue.push(inv);
-            }
-        }
-    }
+    if let Ok(transcript) = fs::read_to_string(TRANSCRIPT) {
+        eprintln!("replaying {}", TRANSCRIPT);
+
+        let commands = transcript.lines().map(String::from).collect::<Vec<_>>();
+        let output = explorer::replay(&mut AsciiMachine::new(&bytes), &commands);
+        println!("{}", output.rsplit("\n\n\n").next().unwrap_or(&output));
 
-    println!("valid inventories:");
-    for valid in can_continue {
-        println!("  {:?}", valid/*.iter().map(|&s| s)*/);
+        return Ok(());
     }
 
+    let mut explorer = explore(&bytes)?;
+    let output = explorer.solve()?;
+
+    println!("carrying {:?}", explorer.inventory());
+    println!("{}", output);
+
+    fs::write(TRANSCRIPT, explorer.transcript().join("\n") + "\n")?;
+
     Ok(())
 }
 
@@ -0,0 +1,171 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use crate::lib::{IntCodeMachine, IntcodeError, State, Word};
+
+pub const MAX_RANGE: usize = 4096; // words shown at once
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    Stepped,
+    Breakpoint(usize),
+    Watchpoint { addr: usize, old: Word, new: Word },
+    AwaitingInput,
+    Halted,
+}
+
+pub struct Debugger {
+    pub machine: IntCodeMachine,
+
+    pub breakpoints: BTreeSet<usize>,
+    pub watchpoints: BTreeSet<usize>,
+
+    pub input: VecDeque<Word>,
+    pub output: Vec<Word>,
+}
+
+impl Debugger {
+    pub fn new(machine: IntCodeMachine) -> Self {
+        Self {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            input: VecDeque::new(),
+            output: Vec::new(),
+        }
+    }
+
+    // returns whether the breakpoint is now set
+    pub fn toggle_breakpoint(&mut self, ip: usize) -> bool {
+        toggle(&mut self.breakpoints, ip)
+    }
+
+    pub fn toggle_watchpoint(&mut self, addr: usize) -> bool {
+        toggle(&mut self.watchpoints, addr)
+    }
+
+    // at most MAX_RANGE words, None if the range runs off the end of the address space
+    pub fn memory_range(&self, start: usize, len: usize) -> Option<Vec<Word>> {
+        let end = start.checked_add(len.min(MAX_RANGE))?;
+
+        Some((start..end)
+            .map(|i| self.machine.peek(i))
+            .collect())
+    }
+
+    pub fn step(&mut self) -> Result<Event, IntcodeError> {
+        if self.machine.state() == State::Halted {
+            return Ok(Event::Halted);
+        }
+
+        let watched = self.watchpoints
+            .iter()
+            .map(|&addr| (addr, self.machine.peek(addr)))
+            .collect::<Vec<_>>();
+
+        self.machine.step(&mut self.input, &mut self.output)?;
+
+        for (addr, old) in watched {
+            let new = self.machine.peek(addr);
+            if new != old {
+                return Ok(Event::Watchpoint { addr, old, new });
+            }
+        }
+
+        Ok(match self.machine.state() {
+            State::Running => Event::Stepped,
+            State::AwaitingInput => Event::AwaitingInput,
+            State::Halted => Event::Halted,
+        })
+    }
+
+    // always executes at least one instruction, so we can continue from a breakpoint
+    pub fn cont(&mut self) -> Result<Event, IntcodeError> {
+        loop {
+            match self.step()? {
+                Event::Stepped => {},
+                event => return Ok(event),
+            }
+
+            let ip = self.machine.ip();
+            if self.breakpoints.contains(&ip) {
+                return Ok(Event::Breakpoint(ip));
+            }
+        }
+    }
+}
+
+fn toggle(set: &mut BTreeSet<usize>, n: usize) -> bool {
+    if set.remove(&n) {
+        false
+    } else {
+        set.insert(n);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // input < 8 ? 999 : input == 8 ? 1000 : 1001 (day 5)
+    const PROG: [Word; 47] = [3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+
+    #[test]
+    fn test_breakpoints() {
+        let mut dbg = Debugger::new(IntCodeMachine::new(&PROG, false));
+
+        assert!(dbg.toggle_breakpoint(31));
+        assert!(dbg.toggle_breakpoint(22));
+        assert!(!dbg.toggle_breakpoint(22));
+
+        assert_eq!(dbg.cont(), Ok(Event::AwaitingInput));
+        dbg.input.push_back(5);
+
+        assert_eq!(dbg.cont(), Ok(Event::Breakpoint(31)));
+        assert_eq!(dbg.output, vec![]);
+
+        assert_eq!(dbg.step(), Ok(Event::Stepped));
+        assert_eq!(dbg.output, vec![999]);
+
+        assert_eq!(dbg.cont(), Ok(Event::Halted));
+        assert_eq!(dbg.step(), Ok(Event::Halted));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut dbg = Debugger::new(IntCodeMachine::new(&PROG, false));
+
+        dbg.toggle_watchpoint(20);
+        dbg.input.push_back(8);
+
+        assert_eq!(dbg.cont(), Ok(Event::Watchpoint { addr: 20, old: 0, new: 1 }));
+        assert_eq!(dbg.machine.ip(), 6);
+
+        assert_eq!(dbg.cont(), Ok(Event::Watchpoint { addr: 20, old: 1, new: 1000 }));
+        assert_eq!(dbg.memory_range(20, 2), Some(vec![1000, 8]));
+    }
+
+    #[test]
+    fn test_memory_range() {
+        let dbg = Debugger::new(IntCodeMachine::new(&PROG, false));
+
+        assert_eq!(dbg.memory_range(0, 3), Some(vec![3, 21, 1008]));
+        assert_eq!(dbg.memory_range(46, 3), Some(vec![99, 0, 0]));
+        assert_eq!(dbg.memory_range(usize::MAX, 1), None);
+        assert_eq!(dbg.memory_range(usize::MAX, 0), Some(vec![]));
+        assert_eq!(dbg.memory_range(0, usize::MAX).map(|r| r.len()), Some(MAX_RANGE));
+    }
+
+    #[test]
+    fn test_poke() {
+        let mut dbg = Debugger::new(IntCodeMachine::new(&PROG, false));
+
+        // replace the input with a no-op and supply the value directly
+        dbg.machine.poke(0, 109).unwrap();
+        dbg.machine.poke(1, 0).unwrap();
+        dbg.machine.poke(21, 9).unwrap();
+
+        assert_eq!(dbg.cont(), Ok(Event::Halted));
+        assert_eq!(dbg.output, vec![1001]);
+    }
+}
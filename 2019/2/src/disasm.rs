@@ -0,0 +1,256 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::lib::{opcode, decode_opcode, operand_mode, Word, OP_JNZ, OP_JZ};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Param {
+    Position(Word),
+    Immediate(Word),
+    RelativeBase(Word),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Item {
+    Isn {
+        addr: usize,
+        isn: Word,
+        mnemonic: &'static str,
+        params: Vec<Param>,
+    },
+    Data {
+        addr: usize,
+        values: Vec<Word>,
+    },
+}
+
+pub struct Disassembly {
+    pub items: Vec<Item>,
+    pub labels: BTreeSet<usize>,
+}
+
+impl Item {
+    pub fn addr(&self) -> usize {
+        match *self {
+            Item::Isn { addr, .. } => addr,
+            Item::Data { addr, .. } => addr,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Item::Isn { params, .. } => 1 + params.len(),
+            Item::Data { values, .. } => values.len(),
+        }
+    }
+}
+
+// None if the word at `addr` isn't a well-formed instruction (or is past the end of `mem`)
+pub fn decode(mem: &[Word], addr: usize) -> Option<Item> {
+    let isn = *mem.get(addr)?;
+    let op = opcode(decode_opcode(isn))?;
+
+    if addr + op.nparams >= mem.len() {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    for i in 0..op.nparams {
+        let operand = mem[addr + 1 + i];
+
+        let param = match operand_mode(isn, i) {
+            0 => Param::Position(operand),
+            1 if op.writes && i == op.nparams - 1 => return None,
+            1 => Param::Immediate(operand),
+            2 => Param::RelativeBase(operand),
+            _ => return None,
+        };
+
+        params.push(param);
+    }
+
+    Some(Item::Isn {
+        addr,
+        isn,
+        mnemonic: op.mnemonic,
+        params,
+    })
+}
+
+fn jump_target(item: &Item) -> Option<usize> {
+    match item {
+        Item::Isn { isn, params, .. } => {
+            let op = decode_opcode(*isn);
+
+            match params.get(1) {
+                Some(&Param::Immediate(target))
+                    if (op == OP_JNZ || op == OP_JZ) && target >= 0
+                    => Some(target as usize),
+                _ => None,
+            }
+        },
+        Item::Data { .. } => None,
+    }
+}
+
+// linear sweep from `start` - anything that doesn't decode is flagged as data
+pub fn disassemble(mem: &[Word], start: usize) -> Disassembly {
+    let mut items = Vec::new();
+    let mut addr = start;
+
+    while addr < mem.len() {
+        let item = match decode(mem, addr) {
+            Some(item) => item,
+            None => {
+                if let Some(Item::Data { values, .. }) = items.last_mut() {
+                    values.push(mem[addr]);
+                } else {
+                    items.push(Item::Data { addr, values: vec![mem[addr]] });
+                }
+                addr += 1;
+                continue;
+            },
+        };
+
+        addr += item.len();
+        items.push(item);
+    }
+
+    let starts = items
+        .iter()
+        .map(Item::addr)
+        .collect::<BTreeSet<_>>();
+
+    let labels = items
+        .iter()
+        .filter_map(jump_target)
+        .filter(|target| starts.contains(target))
+        .collect();
+
+    Disassembly { items, labels }
+}
+
+pub fn label(addr: usize) -> String {
+    format!("L{}", addr)
+}
+
+impl Disassembly {
+    fn param_str(&self, param: &Param, jump: bool) -> String {
+        match *param {
+            Param::Position(pos) => format!("[{}]", pos),
+            Param::Immediate(target)
+                if jump && target >= 0 && self.labels.contains(&(target as usize))
+                => format!("#{}", label(target as usize)),
+            Param::Immediate(val) => format!("#{}", val),
+            Param::RelativeBase(off) if off < 0 => format!("rb{}", off),
+            Param::RelativeBase(off) => format!("rb+{}", off),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            if self.labels.contains(&item.addr()) {
+                writeln!(fmt, "{}:", label(item.addr()))?;
+            }
+
+            let mut line = String::new();
+            match item {
+                Item::Isn { isn, mnemonic, params, .. } => {
+                    let jump = jump_target(item).is_some();
+                    let params = params
+                        .iter()
+                        .enumerate()
+                        .map(|(i, p)| self.param_str(p, jump && i == 1))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    line.push_str(mnemonic);
+                    if !params.is_empty() {
+                        line.push(' ');
+                        line.push_str(&params);
+                    }
+
+                    writeln!(fmt, "    {:<32} ; {} ({})", line, item.addr(), isn)?;
+                },
+                Item::Data { values, .. } => {
+                    let values = values
+                        .iter()
+                        .map(Word::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    line.push_str("data ");
+                    line.push_str(&values);
+
+                    writeln!(fmt, "    {:<32} ; {} undecodable", line, item.addr())?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_modes() {
+        assert_eq!(
+            decode(&[1201, 4, -3, 5], 0),
+            Some(Item::Isn {
+                addr: 0,
+                isn: 1201,
+                mnemonic: "add",
+                params: vec![
+                    Param::RelativeBase(4),
+                    Param::Immediate(-3),
+                    Param::Position(5),
+                ],
+            }));
+
+        // immediate destination
+        assert_eq!(decode(&[11101, 1, 1, 5], 0), None);
+        // bad mode
+        assert_eq!(decode(&[304, 1], 0), None);
+        // truncated
+        assert_eq!(decode(&[1, 0, 0], 0), None);
+        // out of bounds
+        assert_eq!(decode(&[99], 1), None);
+        assert_eq!(decode(&[], 0), None);
+    }
+
+    #[test]
+    fn test_disassemble_labels_and_data() {
+        // input < 8 ? 999 : input == 8 ? 1000 : 1001 (day 5)
+        let mem = [3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+
+        let dis = disassemble(&mem, 0);
+
+        assert_eq!(dis.labels, [22, 31, 36, 46].iter().cloned().collect());
+        assert_eq!(
+            dis.items[5..=7].iter().map(Item::addr).collect::<Vec<_>>(),
+            vec![16, 19, 22]);
+        assert_eq!(dis.items[6], Item::Data { addr: 19, values: vec![98, 0, 0] });
+
+        let text = dis.to_string();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("    in [21] "));
+        assert!(lines[1].starts_with("    eq [21], #8, [20] "));
+        assert!(lines[2].starts_with("    jnz [20], #L22 "));
+        assert!(text.contains("\nL22:\n    mul [21], #125, [20] "));
+        assert!(text.contains("    data 98, 0, 0 "));
+    }
+
+    #[test]
+    fn test_disassemble_relative() {
+        let dis = disassemble(&[109,-1,204,1,99], 0);
+
+        assert_eq!(
+            dis.to_string().lines().map(|l| l.split(';').next().unwrap().trim()).collect::<Vec<_>>(),
+            vec!["arb #-1", "out rb+1", "hlt"]);
+    }
+}
@@ -0,0 +1,515 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::ascii::AsciiMachine;
+
+// explores a day 25 style text adventure: maps every room reachable by doors, picks up
+// everything not on the blocklist, then walks to the security checkpoint and works out which
+// items to carry past the pressure-sensitive floor. every command sent is kept, so the whole
+// run can be replayed. exploring and solving are separate steps, so a run can be checkpointed
+// at the security checkpoint (the game's own snapshot plus `state`) and resumed from there
+
+pub const DEFAULT_BLOCKLIST: [&str; 5] = [
+    "escape pod",
+    "giant electromagnet",
+    "infinite loop",
+    "molten lava",
+    "photons",
+];
+
+pub trait Game: Clone {
+    // sends raw input (possibly empty), returning everything output until the game wants more
+    fn input(&mut self, input: &str) -> String;
+}
+
+impl Game for AsciiMachine {
+    fn input(&mut self, input: &str) -> String {
+        self.run(input.into())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Room {
+    pub name: String,
+    pub description: String,
+    pub doors: Vec<String>,
+    pub items: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ExploreError(pub String);
+
+impl fmt::Display for ExploreError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ExploreError {}
+
+// the last room described in `output` - being bounced from one room to another describes both
+pub fn parse_room(output: &str) -> Option<Room> {
+    let start = output.rfind("== ")?;
+    let mut lines = output[start..].lines();
+
+    let name = lines
+        .next()?
+        .trim_start_matches("== ")
+        .trim_end_matches(" ==")
+        .to_string();
+
+    let mut room = Room {
+        name,
+        description: String::new(),
+        doors: Vec::new(),
+        items: Vec::new(),
+    };
+
+    #[derive(PartialEq)]
+    enum Section { Description, Doors, Items, Other }
+    let mut section = Section::Description;
+
+    for line in lines {
+        match (line, line.strip_prefix("- ")) {
+            ("Doors here lead:", _) => section = Section::Doors,
+            ("Items here:", _) => section = Section::Items,
+            (_, Some(door)) if section == Section::Doors => room.doors.push(door.into()),
+            (_, Some(item)) if section == Section::Items => room.items.push(item.into()),
+            ("", _) => {
+                if section != Section::Description {
+                    section = Section::Other;
+                }
+            },
+            (_, _) if section == Section::Description => {
+                if !room.description.is_empty() {
+                    room.description.push('\n');
+                }
+                room.description.push_str(line);
+            },
+            _ => {},
+        }
+    }
+
+    Some(room)
+}
+
+pub fn opposite(dir: &str) -> Option<&'static str> {
+    match dir {
+        "north" => Some("south"),
+        "south" => Some("north"),
+        "east" => Some("west"),
+        "west" => Some("east"),
+        _ => None,
+    }
+}
+
+pub struct Explorer<G: Game> {
+    game: G,
+    blocklist: HashSet<String>,
+
+    rooms: HashMap<String, Room>,
+    doors: HashMap<(String, String), String>, // (room, direction) -> room
+    here: String,
+    inventory: Vec<String>,
+    checkpoint: Option<(String, String)>, // the room and the door we get bounced back through
+
+    transcript: Vec<String>,
+}
+
+impl<G: Game> Explorer<G> {
+    pub fn new(game: G) -> Self {
+        Self {
+            game,
+            blocklist: DEFAULT_BLOCKLIST.iter().map(|&s| s.into()).collect(),
+            rooms: HashMap::new(),
+            doors: HashMap::new(),
+            here: String::new(),
+            inventory: Vec::new(),
+            checkpoint: None,
+            transcript: Vec::new(),
+        }
+    }
+
+    pub fn set_blocklist(&mut self, items: &[&str]) {
+        self.blocklist = items.iter().map(|&s| s.into()).collect();
+    }
+
+    pub fn rooms(&self) -> &HashMap<String, Room> {
+        &self.rooms
+    }
+
+    pub fn inventory(&self) -> &[String] {
+        &self.inventory
+    }
+
+    pub fn transcript(&self) -> &[String] {
+        &self.transcript
+    }
+
+    pub fn game(&self) -> &G {
+        &self.game
+    }
+
+    // everything but the game needed to resume at the checkpoint, one "key value" per line
+    pub fn state(&self) -> String {
+        let mut lines = Vec::new();
+
+        if let Some((room, door)) = &self.checkpoint {
+            lines.push(format!("room {}", room));
+            lines.push(format!("door {}", door));
+        }
+        lines.extend(self.inventory.iter().map(|item| format!("item {}", item)));
+        lines.extend(self.transcript.iter().map(|command| format!("sent {}", command)));
+
+        lines.join("\n") + "\n"
+    }
+
+    // picks up where `state` was taken, with `game` restored to the same point
+    pub fn resume(game: G, state: &str) -> Result<Self, ExploreError> {
+        let mut explorer = Self::new(game);
+        let (mut room, mut door) = (None, None);
+
+        for line in state.lines() {
+            match line.split_once(' ') {
+                Some(("room", r)) => room = Some(r.to_string()),
+                Some(("door", d)) => door = Some(d.to_string()),
+                Some(("item", item)) => explorer.inventory.push(item.into()),
+                Some(("sent", command)) => explorer.transcript.push(command.into()),
+                _ => return Err(ExploreError(format!("bad explorer state line \"{}\"", line))),
+            }
+        }
+
+        let checkpoint = room
+            .zip(door)
+            .ok_or_else(|| ExploreError("explorer state has no checkpoint".into()))?;
+        explorer.here = checkpoint.0.clone();
+        explorer.checkpoint = Some(checkpoint);
+
+        Ok(explorer)
+    }
+
+    fn send(&mut self, command: &str) -> String {
+        self.transcript.push(command.into());
+        self.game.input(&format!("{}\n", command))
+    }
+
+    // explores, then gets past the checkpoint, returning the game's final output
+    pub fn run(&mut self) -> Result<String, ExploreError> {
+        self.explore()?;
+        self.solve()
+    }
+
+    // maps everything and picks up what we can, ending up at the checkpoint
+    pub fn explore(&mut self) -> Result<(), ExploreError> {
+        let intro = self.game.input("");
+        let start = parse_room(&intro)
+            .ok_or_else(|| ExploreError("no room in the game's initial output".into()))?;
+
+        self.here = start.name.clone();
+        self.visit(start)?;
+
+        let (room, _) = self.checkpoint
+            .clone()
+            .ok_or_else(|| ExploreError("never found the checkpoint".into()))?;
+
+        self.goto(&room)
+    }
+
+    // from the checkpoint, gets past the floor
+    pub fn solve(&mut self) -> Result<String, ExploreError> {
+        let (room, door) = self.checkpoint
+            .clone()
+            .ok_or_else(|| ExploreError("never found the checkpoint".into()))?;
+
+        if self.here != room {
+            return Err(ExploreError(format!("in {}, not at the checkpoint", self.here)));
+        }
+        self.solve_checkpoint(&room, &door)
+    }
+
+    fn visit(&mut self, room: Room) -> Result<(), ExploreError> {
+        for item in &room.items {
+            if self.blocklist.contains(item) {
+                continue;
+            }
+
+            if self.send(&format!("take {}", item)).contains("You take the") {
+                self.inventory.push(item.clone());
+            }
+        }
+
+        let name = room.name.clone();
+        self.rooms.insert(name.clone(), room.clone());
+
+        for dir in &room.doors {
+            if self.doors.contains_key(&(name.clone(), dir.clone())) {
+                continue;
+            }
+            let back = opposite(dir)
+                .ok_or_else(|| ExploreError(format!("unknown direction \"{}\"", dir)))?;
+
+            let output = self.send(dir);
+            let next = parse_room(&output)
+                .ok_or_else(|| ExploreError(format!("no room through {} from {}", dir, name)))?;
+
+            if next.name == name {
+                // ejected straight back
+                self.checkpoint = Some((name.clone(), dir.clone()));
+                continue;
+            }
+
+            self.doors.insert((name.clone(), dir.clone()), next.name.clone());
+            self.doors.insert((next.name.clone(), back.into()), name.clone());
+
+            if !self.rooms.contains_key(&next.name) {
+                self.here = next.name.clone();
+                self.visit(next)?;
+            }
+
+            self.send(back);
+            self.here = name.clone();
+        }
+
+        Ok(())
+    }
+
+    // shortest path through known doors
+    fn goto(&mut self, target: &str) -> Result<(), ExploreError> {
+        let mut prev = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(self.here.clone());
+
+        while let Some(room) = queue.pop_front() {
+            if room == target {
+                break;
+            }
+
+            for ((from, dir), to) in &self.doors {
+                if *from == room && to != &self.here && !prev.contains_key(to) {
+                    prev.insert(to.clone(), (room.clone(), dir.clone()));
+                    queue.push_back(to.clone());
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut room = target.to_string();
+        while room != self.here {
+            let (from, dir) = prev
+                .get(&room)
+                .ok_or_else(|| ExploreError(format!("no path to {}", target)))?;
+
+            path.push(dir.clone());
+            room = from.clone();
+        }
+
+        for dir in path.iter().rev() {
+            self.send(dir);
+        }
+        self.here = target.into();
+
+        Ok(())
+    }
+
+    // tries every subset of the inventory, most items first
+    fn solve_checkpoint(&mut self, room: &str, door: &str) -> Result<String, ExploreError> {
+        let inventory = self.inventory.clone();
+
+        for n in (0..=inventory.len()).rev() {
+            for keep in inventory.iter().combinations(n) {
+                let mut game = self.game.clone();
+                let mut commands = inventory
+                    .iter()
+                    .filter(|item| !keep.contains(item))
+                    .map(|item| format!("drop {}", item))
+                    .collect::<Vec<_>>();
+                commands.push(door.into());
+
+                let output = game.input(&commands.iter().map(|c| format!("{}\n", c)).join(""));
+
+                if parse_room(&output).filter(|r| r.name != room).is_some() {
+                    self.game = game;
+                    self.transcript.extend(commands);
+                    self.inventory = keep.into_iter().cloned().collect();
+                    return Ok(output);
+                }
+            }
+        }
+
+        Err(ExploreError(format!("no combination of {:?} gets past {}", inventory, room)))
+    }
+}
+
+// feeds every command of a transcript to a fresh game
+pub fn replay<G: Game>(game: &mut G, transcript: &[String]) -> String {
+    let input = transcript
+        .iter()
+        .map(|c| format!("{}\n", c))
+        .join("");
+
+    game.input(&input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKPOINT: &str = "Security Checkpoint";
+    const FLOOR: &str = "Pressure-Sensitive Floor";
+
+    // hull breach -north- hallway -east- kitchen
+    //                       |north
+    //                   checkpoint -north- floor (wants exactly the spoon and the mug)
+    #[derive(Clone)]
+    struct Fake {
+        room: &'static str,
+        items: HashMap<&'static str, Vec<String>>,
+        inventory: Vec<String>,
+        molten: bool,
+    }
+
+    impl Fake {
+        fn new() -> Self {
+            let mut items = HashMap::new();
+            items.insert("Hallway", vec!["mug".into(), "anchor".into()]);
+            items.insert("Kitchen", vec!["molten lava".into(), "spoon".into()]);
+
+            Self { room: "Hull Breach", items, inventory: vec![], molten: false }
+        }
+
+        fn doors(room: &str) -> Vec<(&'static str, &'static str)> {
+            match room {
+                "Hull Breach" => vec![("north", "Hallway")],
+                "Hallway" => vec![("south", "Hull Breach"), ("east", "Kitchen"), ("north", CHECKPOINT)],
+                "Kitchen" => vec![("west", "Hallway")],
+                CHECKPOINT => vec![("north", FLOOR), ("south", "Hallway")],
+                _ => vec![],
+            }
+        }
+
+        fn describe(&self, room: &str) -> String {
+            let mut s = format!("\n\n\n== {} ==\nA room.\n\nDoors here lead:\n", room);
+            for (dir, _) in Fake::doors(room) {
+                s += &format!("- {}\n", dir);
+            }
+            if let Some(items) = self.items.get(room).filter(|i| !i.is_empty()) {
+                s += "\nItems here:\n";
+                for item in items {
+                    s += &format!("- {}\n", item);
+                }
+            }
+            s + "\nCommand?\n"
+        }
+
+        fn command(&mut self, cmd: &str) -> String {
+            if let Some(item) = cmd.strip_prefix("take ") {
+                let here = self.items.get_mut(self.room).unwrap();
+                here.retain(|i| i != item);
+                self.molten |= item == "molten lava";
+                self.inventory.push(item.into());
+                return format!("\nYou take the {}.\n\nCommand?\n", item);
+            }
+            if let Some(item) = cmd.strip_prefix("drop ") {
+                self.inventory.retain(|i| i != item);
+                self.items.entry(self.room).or_default().push(item.into());
+                return format!("\nYou drop the {}.\n\nCommand?\n", item);
+            }
+
+            let to = match Fake::doors(self.room).into_iter().find(|&(dir, _)| dir == cmd) {
+                Some((_, to)) => to,
+                None => return "\nYou can't go that way.\n\nCommand?\n".into(),
+            };
+
+            if to == FLOOR {
+                let mut inv = self.inventory.clone();
+                inv.sort();
+                if inv != ["mug", "spoon"] {
+                    return format!("\n\n\n== {} ==\nAnalyzing...\n\nDoors here lead:\n- south\n\nA loud, robotic voice says \"Alert!\" and you are ejected back to the checkpoint.\n{}", FLOOR, self.describe(CHECKPOINT));
+                }
+                return format!("\n\n\n== {} ==\nAnalyzing...\n\nDoors here lead:\n- south\n\nYou may proceed.\n", FLOOR);
+            }
+
+            self.room = to;
+            self.describe(to)
+        }
+    }
+
+    impl Game for Fake {
+        fn input(&mut self, input: &str) -> String {
+            assert!(!self.molten, "melted");
+
+            if input.is_empty() {
+                return self.describe(self.room);
+            }
+            input.lines().map(|cmd| self.command(cmd)).collect()
+        }
+    }
+
+    #[test]
+    fn test_parse_room() {
+        let output = "\n\n\n== Holodeck ==\nSomeone seems to have left it on the Giant Grid setting.\n\nDoors here lead:\n- east\n- south\n- west\n\nItems here:\n- giant electromagnet\n\nCommand?\n";
+
+        assert_eq!(
+            parse_room(output),
+            Some(Room {
+                name: "Holodeck".into(),
+                description: "Someone seems to have left it on the Giant Grid setting.".into(),
+                doors: vec!["east".into(), "south".into(), "west".into()],
+                items: vec!["giant electromagnet".into()],
+            }));
+
+        assert_eq!(parse_room("You can't go that way.\n\nCommand?\n"), None);
+    }
+
+    #[test]
+    fn test_explore_and_solve() {
+        let mut explorer = Explorer::new(Fake::new());
+
+        let output = explorer.run().unwrap();
+        assert!(output.contains("You may proceed."));
+
+        let mut rooms = explorer.rooms().keys().cloned().collect::<Vec<_>>();
+        rooms.sort();
+        assert_eq!(rooms, vec!["Hallway", "Hull Breach", "Kitchen", CHECKPOINT]);
+
+        let mut inventory = explorer.inventory().to_vec();
+        inventory.sort();
+        assert_eq!(inventory, vec!["mug", "spoon"]);
+
+        // the transcript gets a fresh game to the same place
+        let output = replay(&mut Fake::new(), explorer.transcript());
+        assert!(output.ends_with("You may proceed.\n"));
+    }
+
+    #[test]
+    fn test_resume() {
+        let mut explorer = Explorer::new(Fake::new());
+        explorer.explore().unwrap();
+
+        let (game, state) = (explorer.game().clone(), explorer.state());
+        let expected = explorer.solve().unwrap();
+
+        let mut resumed = Explorer::resume(game, &state).unwrap();
+        assert_eq!(resumed.solve(), Ok(expected));
+        assert_eq!(resumed.inventory(), explorer.inventory());
+        assert_eq!(resumed.transcript(), explorer.transcript());
+
+        assert!(Explorer::resume(Fake::new(), "item mug\n").is_err());
+        assert!(Explorer::resume(Fake::new(), "bogus\n").is_err());
+    }
+
+    #[test]
+    fn test_blocklist() {
+        let mut explorer = Explorer::new(Fake::new());
+        explorer.set_blocklist(&["molten lava", "spoon"]);
+
+        assert_eq!(
+            explorer.run(),
+            Err(ExploreError(
+                "no combination of [\"mug\", \"anchor\"] gets past Security Checkpoint".into())));
+    }
+}
@@ -0,0 +1,487 @@
+#![allow(dead_code)]
+
+use std::sync::Arc;
+
+use crate::lib::{
+    decode_opcode, opcode, operand_mode,
+    IntcodeError, IntcodeInput, IntcodeOutput, Memory, Snapshot, State, Stop, Word,
+    OP_ADD, OP_MUL, OP_INPUT, OP_OUTPUT, OP_JNZ, OP_JZ, OP_LT, OP_EQ, OP_RELATIVE_BASE, OP_HALT,
+};
+
+// same semantics (and errors) as IntCodeMachine, but the initial program is decoded once, up
+// front. a write anywhere within a decoded instruction marks it stale, so self-modifying code
+// still sees its own changes (stale instructions are decoded on every execution). no
+// debug/trace support - use IntCodeMachine for that
+//
+// the decoded program is shared between clones, so it's cheap to keep a pristine machine
+// around and clone it per run (see day19)
+
+const MAX_ISN_LEN: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Decoded {
+    isn: Word,
+    op: Word,
+    modes: [u8; 3],
+    operands: [Word; 3],
+    fetched: usize, // operands within the address limit
+}
+
+#[derive(Clone)]
+pub struct FastMachine {
+    state: State,
+    mem: Memory,
+    code: Arc<[Option<Decoded>]>,
+    stale: Vec<u64>, // bitset over `code`
+    ip: usize,
+    relative_base: Word,
+}
+
+impl FastMachine {
+    pub fn new(mem: &[Word]) -> Self {
+        let mut machine = Self {
+            state: State::Running,
+            mem: Memory::new(mem),
+            code: Arc::new([]),
+            stale: vec![0; mem.len().div_ceil(64)],
+            ip: 0,
+            relative_base: 0,
+        };
+        machine.code = (0..mem.len())
+            .map(|addr| machine.decode(addr).filter(|_| addr + MAX_ISN_LEN <= mem.len()))
+            .collect();
+        machine
+    }
+
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        let mut machine = Self::new(&snapshot.mem);
+
+        machine.mem.limit = snapshot.address_limit;
+        for &(addr, w) in &snapshot.sparse {
+            if let Some(ent) = machine.mem.get_mut(addr) {
+                *ent = w;
+            }
+        }
+        machine.state = snapshot.state;
+        machine.ip = snapshot.ip;
+        machine.relative_base = snapshot.relative_base;
+        machine
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state,
+            mem: self.mem.dense.clone(),
+            sparse: self.mem.sparse(),
+            address_limit: self.mem.limit,
+            ip: self.ip,
+            relative_base: self.relative_base,
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn set_address_limit(&mut self, limit: usize) {
+        self.mem.limit = limit;
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    pub fn is_running(&self) -> bool {
+        match self.state {
+            State::Running | State::AwaitingInput => true,
+            State::Halted => false,
+        }
+    }
+
+    pub fn memory(&self) -> &[Word] {
+        &self.mem.dense
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn peek(&self, index: usize) -> Word {
+        self.mem.get(index).unwrap_or(0)
+    }
+
+    pub fn poke(&mut self, index: usize, w: Word) -> Result<(), IntcodeError> {
+        if !self.mem.in_range(index) {
+            return Err(self.out_of_range(index));
+        }
+        self.write(index, w);
+        Ok(())
+    }
+
+    fn out_of_range(&self, address: usize) -> IntcodeError {
+        IntcodeError::OutOfRange { ip: self.ip, address, limit: self.mem.limit }
+    }
+
+    // None for an unknown opcode
+    fn decode(&self, addr: usize) -> Option<Decoded> {
+        let isn = self.mem.get(addr)?;
+        let op = opcode(decode_opcode(isn))?;
+
+        let mut decoded = Decoded {
+            isn,
+            op: op.op,
+            modes: [0; 3],
+            operands: [0; 3],
+            fetched: 0,
+        };
+        for i in 0..op.nparams {
+            decoded.modes[i] = operand_mode(isn, i) as u8;
+            match self.mem.get(addr + 1 + i) {
+                Some(operand) => decoded.operands[i] = operand,
+                None => break,
+            }
+            decoded.fetched += 1;
+        }
+
+        Some(decoded)
+    }
+
+    fn is_stale(&self, addr: usize) -> bool {
+        self.stale[addr / 64] & (1 << (addr % 64)) != 0
+    }
+
+    fn fetch(&self) -> Result<Decoded, IntcodeError> {
+        if let Some(Some(decoded)) = self.code.get(self.ip) {
+            if !self.is_stale(self.ip) {
+                return Ok(*decoded);
+            }
+        }
+
+        self.decode(self.ip).ok_or_else(|| match self.mem.get(self.ip) {
+            Some(isn) => IntcodeError::UnknownOpcode { ip: self.ip, isn },
+            None => self.out_of_range(self.ip),
+        })
+    }
+
+    fn write(&mut self, index: usize, w: Word) {
+        if let Some(ent) = self.mem.get_mut(index) {
+            *ent = w;
+        }
+
+        // every instruction that could span `index`
+        for addr in index.saturating_sub(MAX_ISN_LEN - 1)..=index {
+            if addr < self.code.len() {
+                self.stale[addr / 64] |= 1 << (addr % 64);
+            }
+        }
+    }
+
+    fn address(&self, isn: Word, operand: usize, address: Word) -> Result<usize, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress { ip: self.ip, isn, operand, address });
+        }
+        Ok(address as usize)
+    }
+
+    fn checked(&self, decoded: &Decoded, w: Option<Word>) -> Result<Word, IntcodeError> {
+        w.ok_or(IntcodeError::Overflow { ip: self.ip, isn: decoded.isn })
+    }
+
+    fn operand(&self, decoded: &Decoded, index: usize) -> Result<Word, IntcodeError> {
+        if index < decoded.fetched {
+            Ok(decoded.operands[index])
+        } else {
+            Err(self.out_of_range(self.ip + 1 + index))
+        }
+    }
+
+    fn operand_input(&self, decoded: &Decoded, index: usize) -> Result<Word, IntcodeError> {
+        let operand = self.operand(decoded, index)?;
+
+        let pos = match decoded.modes[index] {
+            0 => operand,
+            1 => return Ok(operand),
+            2 => self.checked(decoded, self.relative_base.checked_add(operand))?,
+            mode => {
+                return Err(IntcodeError::InvalidMode {
+                    ip: self.ip,
+                    isn: decoded.isn,
+                    operand: index,
+                    mode: mode as Word,
+                });
+            },
+        };
+
+        let pos = self.address(decoded.isn, index, pos)?;
+        self.mem.get(pos).ok_or_else(|| self.out_of_range(pos))
+    }
+
+    fn operand_output(&self, decoded: &Decoded, index: usize) -> Result<usize, IntcodeError> {
+        let operand = self.operand(decoded, index)?;
+
+        let pos = match decoded.modes[index] {
+            0 => operand,
+            1 => {
+                return Err(IntcodeError::ImmediateWrite { ip: self.ip, isn: decoded.isn, operand: index });
+            },
+            2 => self.checked(decoded, self.relative_base.checked_add(operand))?,
+            mode => {
+                return Err(IntcodeError::InvalidMode {
+                    ip: self.ip,
+                    isn: decoded.isn,
+                    operand: index,
+                    mode: mode as Word,
+                });
+            },
+        };
+
+        let pos = self.address(decoded.isn, index, pos)?;
+        if !self.mem.in_range(pos) {
+            return Err(self.out_of_range(pos));
+        }
+        Ok(pos)
+    }
+
+    pub fn interpret_async<I: IntcodeInput + ?Sized>(
+        &mut self,
+        inputs: &mut I
+    ) -> Vec<Word> {
+        let mut output = Vec::new();
+
+        if let Err(e) = self.try_interpret_async(inputs, &mut output) {
+            panic!("{}", e);
+        }
+
+        output
+    }
+
+    // runs until halt or we block on input, returning which. outputs go straight to
+    // `outputs`, so anything output before a fault is kept
+    pub fn try_interpret_async<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<State, IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Blocked)?;
+
+        Ok(self.state)
+    }
+
+    // runs until the next output, returning None if we halt or block on input first
+    pub fn try_interpret_until_output<I: IntcodeInput + ?Sized>(
+        &mut self,
+        inputs: &mut I
+    ) -> Result<Option<Word>, IntcodeError> {
+        let mut output = Vec::new();
+
+        self.execute(inputs, &mut output, Stop::Output)?;
+
+        Ok(output.pop())
+    }
+
+    pub fn try_interpret_io<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Blocked)
+    }
+
+    // executes a single instruction - if it needs input and there is none, the machine is left AwaitingInput
+    pub fn step<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Step)
+    }
+
+    fn execute<I, O>(
+        &mut self,
+        inputs: &mut I,
+        output: &mut O,
+        stop: Stop,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        match self.state {
+            State::Running | State::AwaitingInput => self.state = State::Running,
+            State::Halted => return Err(IntcodeError::Halted { ip: self.ip }),
+        }
+
+        loop {
+            let isn = self.fetch()?;
+
+            match isn.op {
+                OP_ADD | OP_MUL | OP_LT | OP_EQ => {
+                    let (lhs, rhs) = (
+                        self.operand_input(&isn, 0)?,
+                        self.operand_input(&isn, 1)?,
+                    );
+                    let dest = self.operand_output(&isn, 2)?;
+
+                    let result = match isn.op {
+                        OP_ADD => self.checked(&isn, lhs.checked_add(rhs))?,
+                        OP_MUL => self.checked(&isn, lhs.checked_mul(rhs))?,
+                        OP_LT => (lhs < rhs) as _,
+                        _ => (lhs == rhs) as _,
+                    };
+                    self.write(dest, result);
+
+                    self.ip += 4;
+                },
+
+                OP_INPUT => {
+                    let dest = self.operand_output(&isn, 0)?;
+
+                    match inputs.next_input() {
+                        Some(input) => self.write(dest, input),
+                        None => {
+                            self.state = State::AwaitingInput;
+                            break;
+                        },
+                    }
+
+                    self.ip += 2;
+                },
+
+                OP_OUTPUT => {
+                    output.output(self.operand_input(&isn, 0)?);
+
+                    self.ip += 2;
+
+                    if stop == Stop::Output {
+                        break;
+                    }
+                },
+
+                OP_JNZ | OP_JZ => {
+                    let (to_test, target) = (
+                        self.operand_input(&isn, 0)?,
+                        self.operand_input(&isn, 1)?,
+                    );
+
+                    self.ip = if (to_test != 0) == (isn.op == OP_JNZ) {
+                        self.address(isn.isn, 1, target)?
+                    } else {
+                        self.ip + 3
+                    };
+                },
+
+                OP_RELATIVE_BASE => {
+                    let operand = self.operand_input(&isn, 0)?;
+                    self.relative_base = self.checked(&isn, self.relative_base.checked_add(operand))?;
+
+                    self.ip += 2;
+                },
+
+                OP_HALT => {
+                    self.state = State::Halted;
+                    break;
+                },
+
+                _ => unreachable!("decode only yields known opcodes"),
+            }
+
+            if stop == Stop::Step {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lib::IntCodeMachine;
+
+    // runs both engines to completion, checking they agree on everything observable
+    fn compare(mem: &[Word], input: &[Word]) -> (Vec<Word>, Result<State, IntcodeError>) {
+        let mut slow = IntCodeMachine::new(mem, false);
+        let mut fast = FastMachine::new(mem);
+
+        let (mut expected, mut actual) = (Vec::new(), Vec::new());
+        let expected_state = slow.try_interpret_async(&mut input.to_vec(), &mut expected);
+        let actual_state = fast.try_interpret_async(&mut input.to_vec(), &mut actual);
+
+        assert_eq!((&actual, &actual_state), (&expected, &expected_state));
+        assert_eq!(fast.snapshot(), slow.snapshot());
+        assert_eq!(fast.memory().len(), slow.memory().len());
+
+        (actual, actual_state)
+    }
+
+    #[test]
+    fn test_matches_interpreter() {
+        // input < 8 ? 999 : input == 8 ? 1000 : 1001 (day 5)
+        let day5 = [3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99];
+        for input in 6..10 {
+            compare(&day5, &[input]).1.unwrap();
+        }
+
+        let quine = [109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+        assert_eq!(compare(&quine, &[]), (quine.to_vec(), Ok(State::Halted)));
+
+        // blocks awaiting input
+        assert_eq!(compare(&[3,0,3,1,99], &[7]), (vec![], Ok(State::AwaitingInput)));
+
+        // the input destination is only range checked before blocking - memory grows once
+        // the input actually arrives
+        assert_eq!(compare(&[3,100,99], &[]), (vec![], Ok(State::AwaitingInput)));
+        assert_eq!(compare(&[3,100,99], &[5]), (vec![], Ok(State::Halted)));
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // the first pass patches its add at address 0 into a mul, so the second outputs 2 * 3
+        let mem = [
+            1101,2,3,30,     // [30] = 2 + 3
+            4,30,            // out [30]
+            1101,0,1102,0,   // [0] = 1102
+            1001,31,1,31,    // [31] += 1
+            1008,31,2,32,    // [32] = [31] == 2
+            1006,32,0,       // jz [32], #0
+            99,
+            0,0,0,0,0,0,0,0,0,0,0,
+        ];
+
+        assert_eq!(compare(&mem, &[]), (vec![5, 6], Ok(State::Halted)));
+
+        // patching an operand rather than the opcode: out [4] becomes out [6]
+        let mem = [4,4,1101,5,0,1,99];
+        let mut fast = FastMachine::new(&mem);
+        fast.poke(1, 6).unwrap();
+        assert_eq!(fast.interpret_async(&mut vec![]), vec![99]);
+    }
+
+    #[test]
+    fn test_errors_match() {
+        assert!(compare(&[1,0,0,0,42], &[]).1.is_err());
+        assert!(compare(&[11101,1,1,5,99], &[]).1.is_err());
+        assert!(compare(&[30001,0,0,0,99], &[]).1.is_err());
+        assert!(compare(&[109,-10,204,0,99], &[]).1.is_err());
+        assert!(compare(&[1101,Word::MAX,1,0,99], &[]).1.is_err());
+        assert!(compare(&[109,Word::MIN,109,-1,99], &[]).1.is_err());
+        assert_eq!(compare(&[104,7,42], &[]).0, vec![7]);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let huge = 1 << 20;
+        compare(&[1101,2,3,huge,4,huge,4,huge + 1,99], &[]).1.unwrap();
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use crate::d2::Coord;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Grid<T> {
     pub map: HashMap<Coord, T>,
     pub default: Option<T>,
@@ -1,3 +1,6 @@
+use std::io::{self, Read, Write};
+use std::process::Command;
+
 pub fn with_tty_io<F, R>(f: F) -> R
     where F: FnOnce() -> R
 {
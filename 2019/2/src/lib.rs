@@ -1,17 +1,47 @@
 #![allow(dead_code)]
 
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
 pub type Word = i64; // may be signed
 
-const OP_ADD: Word = 1; // *[3] = *[1] + *[2]
-const OP_MUL: Word = 2; // *[3] = *[1] + *[2]
-const OP_HALT: Word = 99; // no arg
-const OP_INPUT: Word = 3; // [1] --> output
-const OP_OUTPUT: Word = 4; // input --> [1]
-const OP_JNZ: Word = 5; // [1] && jmp [2]
-const OP_JZ: Word = 6; // [1] == 0 && jmp [2]
-const OP_LT: Word = 7; // [1] < [2] --> [3]
-const OP_EQ: Word = 8; // [1] == [2] --> [3]
-const OP_RELATIVE_BASE: Word = 9; // [1] == [2] --> [3]
+pub const OP_ADD: Word = 1; // *[3] = *[1] + *[2]
+pub const OP_MUL: Word = 2; // *[3] = *[1] + *[2]
+pub const OP_HALT: Word = 99; // no arg
+pub const OP_INPUT: Word = 3; // [1] --> output
+pub const OP_OUTPUT: Word = 4; // input --> [1]
+pub const OP_JNZ: Word = 5; // [1] && jmp [2]
+pub const OP_JZ: Word = 6; // [1] == 0 && jmp [2]
+pub const OP_LT: Word = 7; // [1] < [2] --> [3]
+pub const OP_EQ: Word = 8; // [1] == [2] --> [3]
+pub const OP_RELATIVE_BASE: Word = 9; // [1] == [2] --> [3]
+
+pub struct Opcode {
+    pub op: Word,
+    pub mnemonic: &'static str,
+    pub nparams: usize,
+    pub writes: bool, // last param is a destination
+}
+
+pub static OPCODES: [Opcode; 10] = [
+    Opcode { op: OP_ADD, mnemonic: "add", nparams: 3, writes: true },
+    Opcode { op: OP_MUL, mnemonic: "mul", nparams: 3, writes: true },
+    Opcode { op: OP_INPUT, mnemonic: "in", nparams: 1, writes: true },
+    Opcode { op: OP_OUTPUT, mnemonic: "out", nparams: 1, writes: false },
+    Opcode { op: OP_JNZ, mnemonic: "jnz", nparams: 2, writes: false },
+    Opcode { op: OP_JZ, mnemonic: "jz", nparams: 2, writes: false },
+    Opcode { op: OP_LT, mnemonic: "lt", nparams: 3, writes: true },
+    Opcode { op: OP_EQ, mnemonic: "eq", nparams: 3, writes: true },
+    Opcode { op: OP_RELATIVE_BASE, mnemonic: "arb", nparams: 1, writes: false },
+    Opcode { op: OP_HALT, mnemonic: "hlt", nparams: 0, writes: false },
+];
+
+pub fn opcode(op: Word) -> Option<&'static Opcode> {
+    OPCODES.iter().find(|o| o.op == op)
+}
 
 macro_rules! debug {
     ( $self: ident, $fmt: literal) => {
@@ -31,21 +61,337 @@ macro_rules! debug {
     };
 }
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum IntcodeError {
+    UnknownOpcode { ip: usize, isn: Word },
+    InvalidMode { ip: usize, isn: Word, operand: usize, mode: Word },
+    ImmediateWrite { ip: usize, isn: Word, operand: usize },
+    NegativeAddress { ip: usize, isn: Word, operand: usize, address: Word },
+    OutOfRange { ip: usize, address: usize, limit: usize },
+    Overflow { ip: usize, isn: Word },
+    Halted { ip: usize },
+}
+
+impl std::fmt::Display for IntcodeError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        match self {
+            IntcodeError::UnknownOpcode { ip, isn } => {
+                write!(fmt, "unknown isn {} at ip {}", isn, ip)
+            },
+            IntcodeError::InvalidMode { ip, isn, operand, mode } => {
+                write!(fmt, "invalid mode {} for operand {} of isn {} at ip {}", mode, operand, isn, ip)
+            },
+            IntcodeError::ImmediateWrite { ip, isn, operand } => {
+                write!(fmt, "can't output to immediate operand {} of isn {} at ip {}", operand, isn, ip)
+            },
+            IntcodeError::NegativeAddress { ip, isn, operand, address } => {
+                write!(fmt, "negative address {} from operand {} of isn {} at ip {}", address, operand, isn, ip)
+            },
+            IntcodeError::OutOfRange { ip, address, limit } => {
+                write!(fmt, "address {} out of range (limit {}) at ip {}", address, limit, ip)
+            },
+            IntcodeError::Overflow { ip, isn } => {
+                write!(fmt, "arithmetic overflow in isn {} at ip {}", isn, ip)
+            },
+            IntcodeError::Halted { ip } => {
+                write!(fmt, "machine halted at ip {}", ip)
+            },
+        }
+    }
+}
+
+impl std::error::Error for IntcodeError {}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum State {
     Running,
+    AwaitingInput,
     Halted,
 }
 
+pub trait IntcodeInput {
+    // None means no input is available yet, and the machine will wait for more
+    fn next_input(&mut self) -> Option<Word>;
+}
+
+pub trait IntcodeOutput {
+    fn output(&mut self, w: Word);
+}
+
+// inputs are consumed from the front, which is O(n) - prefer VecDeque
+impl IntcodeInput for Vec<Word> {
+    fn next_input(&mut self) -> Option<Word> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.remove(0))
+        }
+    }
+}
+
+impl IntcodeInput for VecDeque<Word> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.pop_front()
+    }
+}
+
+// blocks until a word arrives, or all senders are gone
+impl IntcodeInput for Receiver<Word> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.recv().ok()
+    }
+}
+
+impl IntcodeOutput for Vec<Word> {
+    fn output(&mut self, w: Word) {
+        self.push(w);
+    }
+}
+
+impl IntcodeOutput for VecDeque<Word> {
+    fn output(&mut self, w: Word) {
+        self.push_back(w);
+    }
+}
+
+// output is dropped if the receiver has gone away
+impl IntcodeOutput for Sender<Word> {
+    fn output(&mut self, w: Word) {
+        let _ = self.send(w);
+    }
+}
+
+pub struct IterInput<I>(pub I);
+
+impl<I: Iterator<Item = Word>> IntcodeInput for IterInput<I> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.0.next()
+    }
+}
+
+pub struct FnInput<F>(pub F);
+
+impl<F: FnMut() -> Option<Word>> IntcodeInput for FnInput<F> {
+    fn next_input(&mut self) -> Option<Word> {
+        (self.0)()
+    }
+}
+
+pub struct FnOutput<F>(pub F);
+
+impl<F: FnMut(Word)> IntcodeOutput for FnOutput<F> {
+    fn output(&mut self, w: Word) {
+        (self.0)(w)
+    }
+}
+
+const PAGE_SIZE: usize = 1024;
+pub(crate) const DENSE_MAX: usize = 1 << 16; // past this (or the program's size), memory is paged
+pub const DEFAULT_ADDRESS_LIMIT: usize = 1 << 32;
+
+#[derive(Clone)]
+pub(crate) struct Memory {
+    pub(crate) dense: Vec<Word>,
+    pages: HashMap<usize, Box<[Word]>>,
+    pub(crate) limit: usize,
+}
+
+impl Memory {
+    pub(crate) fn new(mem: &[Word]) -> Self {
+        Self {
+            dense: From::from(mem),
+            pages: HashMap::new(),
+            limit: DEFAULT_ADDRESS_LIMIT,
+        }
+    }
+
+    pub(crate) fn in_range(&self, index: usize) -> bool {
+        index < self.dense.len() || index < self.limit
+    }
+
+    // None if out of range
+    pub(crate) fn get(&self, index: usize) -> Option<Word> {
+        if index < self.dense.len() {
+            return Some(self.dense[index]);
+        }
+        if index >= self.limit {
+            return None;
+        }
+
+        Some(
+            self.pages
+                .get(&(index / PAGE_SIZE))
+                .map_or(0, |page| page[index % PAGE_SIZE]))
+    }
+
+    pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut Word> {
+        if index < self.dense.len() {
+            return Some(&mut self.dense[index]);
+        }
+        if index >= self.limit {
+            return None;
+        }
+
+        if index < DENSE_MAX {
+            self.dense.resize(index + 1, 0);
+            return Some(&mut self.dense[index]);
+        }
+
+        let page = self.pages
+            .entry(index / PAGE_SIZE)
+            .or_insert_with(|| vec![0; PAGE_SIZE].into_boxed_slice());
+
+        Some(&mut page[index % PAGE_SIZE])
+    }
+
+    fn load(&mut self, mem: &[Word]) {
+        self.dense.clear();
+        self.dense.extend_from_slice(mem);
+        self.pages.clear();
+    }
+
+    // non-zero words held in pages, i.e. outside `dense`
+    pub(crate) fn sparse(&self) -> Vec<(usize, Word)> {
+        let mut words = self.pages
+            .iter()
+            .flat_map(|(&page, words)| {
+                words
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, &w)| (page * PAGE_SIZE + i, w))
+            })
+            .filter(|&(addr, w)| w != 0 && addr >= self.dense.len())
+            .collect::<Vec<_>>();
+
+        words.sort();
+        words
+    }
+}
+
+// everything needed to resume a machine, plus any I/O the caller had queued
+#[derive(Debug, PartialEq, Clone)]
+pub struct Snapshot {
+    pub state: State,
+    pub mem: Vec<Word>,
+    pub sparse: Vec<(usize, Word)>, // paged memory beyond `mem`
+    pub address_limit: usize,
+    pub ip: usize,
+    pub relative_base: Word,
+
+    pub input: Vec<Word>,
+    pub output: Vec<Word>,
+}
+
+const SNAPSHOT_MAGIC: &str = "intcode-snapshot 1";
+
+fn words_str(words: &[Word]) -> String {
+    words.iter().map(Word::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl Snapshot {
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("snapshot: {}", what));
+
+        let mut lines = s.lines();
+        if lines.next() != Some(SNAPSHOT_MAGIC) {
+            return Err(invalid("bad header"));
+        }
+
+        let mut field = |name: &str| {
+            lines
+                .next()
+                .and_then(|l| l.strip_prefix(name))
+                .map(str::trim)
+                .ok_or_else(|| invalid(&format!("missing {}", name)))
+        };
+        let words = |s: &str| {
+            s.split(',')
+                .filter(|w| !w.is_empty())
+                .map(str::parse)
+                .collect::<Result<Vec<Word>, _>>()
+                .map_err(|_| invalid("bad word"))
+        };
+
+        let state = match field("state")? {
+            "running" => State::Running,
+            "awaiting-input" => State::AwaitingInput,
+            "halted" => State::Halted,
+            _ => return Err(invalid("bad state")),
+        };
+        let ip = field("ip")?.parse().map_err(|_| invalid("bad ip"))?;
+        let relative_base = field("relative_base")?.parse().map_err(|_| invalid("bad relative_base"))?;
+        let address_limit = field("address_limit")?.parse().map_err(|_| invalid("bad address_limit"))?;
+        let input = words(field("input")?)?;
+        let output = words(field("output")?)?;
+        let mem = words(field("mem")?)?;
+        let sparse = field("sparse")?
+            .split(',')
+            .filter(|w| !w.is_empty())
+            .map(|ent| {
+                let mut parts = ent.splitn(2, '=');
+                match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+                    (Some(Ok(addr)), Some(Ok(w))) => Ok((addr, w)),
+                    _ => Err(invalid("bad sparse word")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { state, mem, sparse, address_limit, ip, relative_base, input, output })
+    }
+}
+
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let state = match self.state {
+            State::Running => "running",
+            State::AwaitingInput => "awaiting-input",
+            State::Halted => "halted",
+        };
+
+        writeln!(fmt, "{}", SNAPSHOT_MAGIC)?;
+        writeln!(fmt, "state {}", state)?;
+        writeln!(fmt, "ip {}", self.ip)?;
+        writeln!(fmt, "relative_base {}", self.relative_base)?;
+        writeln!(fmt, "address_limit {}", self.address_limit)?;
+        writeln!(fmt, "input {}", words_str(&self.input))?;
+        writeln!(fmt, "output {}", words_str(&self.output))?;
+        writeln!(fmt, "mem {}", words_str(&self.mem))?;
+        writeln!(
+            fmt,
+            "sparse {}",
+            self.sparse
+                .iter()
+                .map(|(addr, w)| format!("{}={}", addr, w))
+                .collect::<Vec<_>>()
+                .join(","))
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub(crate) enum Stop {
+    Blocked,
+    Output,
+    Step,
+}
+
 #[derive(Clone)]
 pub struct IntCodeMachine {
     state: State,
 
-    mem: Vec<Word>,
+    mem: Memory,
     ip: usize,
     relative_base: Word,
 
     debug: bool,
+    trace: Option<Arc<Mutex<dyn Write + Send>>>, // shared between clones
 }
 
 #[derive(Debug)]
@@ -55,7 +401,7 @@ enum Operand {
     RelativeBase(Word),
 }
 
-fn operand_mode(op: Word, iparam: usize) -> Word {
+pub fn operand_mode(op: Word, iparam: usize) -> Word {
     let mut paramcodes = op / 100;
 
     for _ in 0 .. iparam {
@@ -65,7 +411,7 @@ fn operand_mode(op: Word, iparam: usize) -> Word {
     paramcodes % 10
 }
 
-fn decode_opcode(op: Word) -> Word {
+pub fn decode_opcode(op: Word) -> Word {
     op % 100
 }
 
@@ -73,73 +419,197 @@ impl IntCodeMachine {
     pub fn new(mem: &[Word], debug: bool) -> Self {
         Self {
             state: State::Running,
-            mem: From::from(mem),
+            mem: Memory::new(mem),
             ip: 0,
             relative_base: 0,
             debug,
+            trace: None,
         }
     }
 
+    // log each executed instruction as a JSON line:
+    // {"ip":4,"isn":1002,"op":"mul","operands":[33,3],"write":[4,99]}
+    // "write" is the address and value stored, "rb" the new relative base
+    pub fn set_trace<W: Write + Send + 'static>(&mut self, w: W) {
+        self.trace = Some(Arc::new(Mutex::new(w)));
+    }
+
+    pub fn trace_to_file(&mut self, path: &str) -> io::Result<()> {
+        self.set_trace(BufWriter::new(File::create(path)?));
+        Ok(())
+    }
+
+    pub fn flush_trace(&self) -> io::Result<()> {
+        match &self.trace {
+            Some(trace) => trace.lock().unwrap().flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn trace(&self, isn: Word, operands: &[Word], write: Option<(Word, Word)>, relative_base: Option<Word>) {
+        let trace = match &self.trace {
+            Some(trace) => trace,
+            None => return,
+        };
+
+        let mut line = format!(
+            "{{\"ip\":{},\"isn\":{},\"op\":\"{}\",\"operands\":[{}]",
+            self.ip,
+            isn,
+            opcode(decode_opcode(isn)).map(|o| o.mnemonic).unwrap_or("?"),
+            operands.iter().map(Word::to_string).collect::<Vec<_>>().join(","));
+
+        if let Some((addr, value)) = write {
+            line.push_str(&format!(",\"write\":[{},{}]", addr, value));
+        }
+        if let Some(rb) = relative_base {
+            line.push_str(&format!(",\"rb\":{}", rb));
+        }
+        line.push('}');
+
+        // tracing is best-effort, don't fault the machine over it
+        let _ = writeln!(trace.lock().unwrap(), "{}", line);
+    }
+
+    pub fn from_snapshot(snapshot: &Snapshot, debug: bool) -> Self {
+        let mut machine = Self::new(&[], debug);
+        machine.restore(snapshot);
+        machine
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state,
+            mem: self.mem.dense.clone(),
+            sparse: self.mem.sparse(),
+            address_limit: self.mem.limit,
+            ip: self.ip,
+            relative_base: self.relative_base,
+            input: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.state = snapshot.state;
+        self.load_memory(&snapshot.mem);
+        self.mem.limit = snapshot.address_limit;
+        for &(addr, w) in &snapshot.sparse {
+            if let Some(ent) = self.mem.get_mut(addr) {
+                *ent = w;
+            }
+        }
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base;
+    }
+
     pub fn state(&self) -> State {
         self.state
     }
 
     pub fn is_running(&self) -> bool {
         match self.state() {
-            State::Running => true,
+            State::Running | State::AwaitingInput => true,
             State::Halted => false,
         }
     }
 
+    // only the dense part of memory, any paged words at huge addresses aren't included
     pub fn memory(&self) -> &[Word] {
-        &self.mem
+        &self.mem.dense
+    }
+
+    // accesses at or beyond `limit` fault with IntcodeError::OutOfRange
+    pub fn set_address_limit(&mut self, limit: usize) {
+        self.mem.limit = limit;
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn relative_base(&self) -> Word {
+        self.relative_base
+    }
+
+    pub fn peek(&self, index: usize) -> Word {
+        self.mem.get(index).unwrap_or(0)
+    }
+
+    pub fn poke(&mut self, index: usize, w: Word) -> Result<(), IntcodeError> {
+        *self.memref(index)? = w;
+        Ok(())
     }
 
     pub fn load_memory(&mut self, mem: &[Word]) {
-        self.mem.clear();
-        self.mem.extend_from_slice(mem);
+        self.mem.load(mem);
     }
 
-    fn memref(&mut self, index: usize) -> &mut Word {
-        if index >= self.mem.len() {
-            self.mem.resize(index + 1, 0);
-        }
-        &mut self.mem[index]
+    fn out_of_range(&self, address: usize) -> IntcodeError {
+        IntcodeError::OutOfRange { ip: self.ip, address, limit: self.mem.limit }
     }
 
-    fn mem(&mut self, index: usize) -> Word {
-        *self.memref(index)
+    fn memref(&mut self, index: usize) -> Result<&mut Word, IntcodeError> {
+        let err = self.out_of_range(index);
+        self.mem.get_mut(index).ok_or(err)
     }
 
-    fn decode_operand(&mut self, index: usize) -> Operand {
-        let op = self.mem(self.ip);
-        let operand = self.mem(self.ip + 1 + index);
+    fn mem(&self, index: usize) -> Result<Word, IntcodeError> {
+        self.mem.get(index).ok_or_else(|| self.out_of_range(index))
+    }
 
-        match operand_mode(op, index) {
-            0 => Operand::Position(operand),
-            1 => Operand::Immediate(operand),
-            2 => Operand::RelativeBase(operand),
-            _ => panic!(),
+    fn address(&self, isn: Word, operand: usize, address: Word) -> Result<usize, IntcodeError> {
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress { ip: self.ip, isn, operand, address });
         }
+        Ok(address as usize)
+    }
+
+    fn checked(&self, isn: Word, w: Option<Word>) -> Result<Word, IntcodeError> {
+        w.ok_or(IntcodeError::Overflow { ip: self.ip, isn })
     }
 
-    fn operand_input(&mut self, index: usize) -> Word {
-        match self.decode_operand(index) {
-            Operand::Position(pos) => self.mem(pos as usize),
-            Operand::Immediate(val) => val,
-            Operand::RelativeBase(val) => self.mem((self.relative_base + val) as usize),
+    fn decode_operand(&mut self, isn: Word, index: usize) -> Result<Operand, IntcodeError> {
+        let operand = self.mem(self.ip + 1 + index)?;
+
+        match operand_mode(isn, index) {
+            0 => Ok(Operand::Position(operand)),
+            1 => Ok(Operand::Immediate(operand)),
+            2 => Ok(Operand::RelativeBase(operand)),
+            mode => Err(IntcodeError::InvalidMode { ip: self.ip, isn, operand: index, mode }),
         }
     }
 
-    fn operand_output(&mut self, index: usize) -> (&mut Word, Word) {
-        let op = self.decode_operand(index);
-        let pos = match op {
+    fn operand_input(&mut self, isn: Word, index: usize) -> Result<Word, IntcodeError> {
+        let pos = match self.decode_operand(isn, index)? {
+            Operand::Position(pos) => pos,
+            Operand::Immediate(val) => return Ok(val),
+            Operand::RelativeBase(val) => self.checked(isn, self.relative_base.checked_add(val))?,
+        };
+
+        let pos = self.address(isn, index, pos)?;
+        self.mem(pos)
+    }
+
+    // only range checked - memory isn't touched (or grown) until the write
+    fn operand_output(&mut self, isn: Word, index: usize) -> Result<usize, IntcodeError> {
+        let pos = match self.decode_operand(isn, index)? {
             Operand::Position(w) => w,
-            Operand::Immediate(_) => panic!("can't output to {:?}", op),
-            Operand::RelativeBase(w) => self.relative_base + w,
+            Operand::Immediate(_) => {
+                return Err(IntcodeError::ImmediateWrite { ip: self.ip, isn, operand: index });
+            },
+            Operand::RelativeBase(w) => self.checked(isn, self.relative_base.checked_add(w))?,
         };
 
-        (self.memref(pos as usize), pos)
+        let i = self.address(isn, index, pos)?;
+        if !self.mem.in_range(i) {
+            return Err(self.out_of_range(i));
+        }
+        Ok(i)
+    }
+
+    fn jump_target(&self, isn: Word, target: Word) -> Result<usize, IntcodeError> {
+        self.address(isn, 1, target)
     }
 
     /*
@@ -151,155 +621,271 @@ impl IntCodeMachine {
     }
     */
 
-    pub fn interpret_async(
+    pub fn interpret_async<I: IntcodeInput + ?Sized>(
         &mut self,
-        inputs: &mut Vec<Word>
+        inputs: &mut I
     ) -> Vec<Word> {
-        match self.state {
-            State::Running => {},
-            State::Halted => panic!(),
+        let mut output = Vec::new();
+
+        if let Err(e) = self.try_interpret_async(inputs, &mut output) {
+            panic!("{}", e);
+        }
+
+        output
+    }
+
+    // runs until halt or we block on input, returning which. outputs go straight to
+    // `outputs`, so anything output before a fault is kept
+    pub fn try_interpret_async<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<State, IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Blocked)?;
+
+        Ok(self.state)
+    }
+
+    // runs until the next output, returning None if we halt or block on input first
+    pub fn interpret_until_output<I: IntcodeInput + ?Sized>(
+        &mut self,
+        inputs: &mut I
+    ) -> Option<Word> {
+        match self.try_interpret_until_output(inputs) {
+            Ok(output) => output,
+            Err(e) => panic!("{}", e),
         }
+    }
 
+    pub fn try_interpret_until_output<I: IntcodeInput + ?Sized>(
+        &mut self,
+        inputs: &mut I
+    ) -> Result<Option<Word>, IntcodeError> {
         let mut output = Vec::new();
 
+        self.execute(inputs, &mut output, Stop::Output)?;
+
+        Ok(output.pop())
+    }
+
+    // runs until halt or input is exhausted, sending outputs directly to `outputs`
+    pub fn interpret_io<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    )
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        if let Err(e) = self.try_interpret_io(inputs, outputs) {
+            panic!("{}", e);
+        }
+    }
+
+    pub fn try_interpret_io<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Blocked)
+    }
+
+    // executes a single instruction - if it needs input and there is none, the machine is left AwaitingInput
+    pub fn step<I, O>(
+        &mut self,
+        inputs: &mut I,
+        outputs: &mut O,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        self.execute(inputs, outputs, Stop::Step)
+    }
+
+    fn execute<I, O>(
+        &mut self,
+        inputs: &mut I,
+        output: &mut O,
+        stop: Stop,
+    ) -> Result<(), IntcodeError>
+        where I: IntcodeInput + ?Sized,
+              O: IntcodeOutput + ?Sized,
+    {
+        match self.state {
+            State::Running | State::AwaitingInput => self.state = State::Running,
+            State::Halted => return Err(IntcodeError::Halted { ip: self.ip }),
+        }
+
         loop {
-            let isn = self.mem(self.ip);
+            let isn = self.mem(self.ip)?;
 
             match decode_opcode(isn) {
                 OP_ADD => {
                     let (lhs, rhs) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
-                    let (dest, dest_i) = self.operand_output(2);
-                    *dest = lhs + rhs;
+                    let dest = self.operand_output(isn, 2)?;
+                    let sum = self.checked(isn, lhs.checked_add(rhs))?;
+                    *self.memref(dest)? = sum;
 
-                    debug!(self, "{} + {} --> [{}]", lhs, rhs, dest_i);
+                    debug!(self, "{} + {} --> [{}]", lhs, rhs, dest);
+                    self.trace(isn, &[lhs, rhs], Some((dest as Word, sum)), None);
 
                     self.ip += 4;
                 },
 
                 OP_MUL => {
                     let (lhs, rhs) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
-                    let (dest, dest_i) = self.operand_output(2);
-                    *dest = lhs * rhs;
+                    let dest = self.operand_output(isn, 2)?;
+                    let product = self.checked(isn, lhs.checked_mul(rhs))?;
+                    *self.memref(dest)? = product;
 
-                    debug!(self, "{} * {} --> [{}]", lhs, rhs, dest_i);
+                    debug!(self, "{} * {} --> [{}]", lhs, rhs, dest);
+                    self.trace(isn, &[lhs, rhs], Some((dest as Word, product)), None);
 
                     self.ip += 4;
                 },
 
                 OP_INPUT => {
-                    let (dest, dest_i) = self.operand_output(0);
-
-                    if inputs.len() == 0 {
-                        break;
+                    let dest = self.operand_output(isn, 0)?;
+
+                    let input = match inputs.next_input() {
+                        Some(input) => input,
+                        None => {
+                            self.state = State::AwaitingInput;
+                            break;
+                        },
                     };
+                    *self.memref(dest)? = input;
 
-                    let input = inputs.remove(0);
-                    *dest = input;
-
-                    debug!(self, "input {} --> [{}]", input, dest_i);
+                    debug!(self, "input {} --> [{}]", input, dest);
+                    self.trace(isn, &[], Some((dest as Word, input)), None);
 
                     self.ip += 2;
                 },
 
                 OP_OUTPUT => {
-                    let src = self.operand_input(0);
+                    let src = self.operand_input(isn, 0)?;
 
                     debug!(self, "output {}", src);
+                    self.trace(isn, &[src], None, None);
 
-                    output.push(src);
+                    output.output(src);
 
                     self.ip += 2;
+
+                    if stop == Stop::Output {
+                        break;
+                    }
                 },
 
                 OP_JNZ => {
                     let (to_test, target) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
                     debug!(self, "jnz {} --> {}", to_test, target);
 
-                    if to_test != 0 {
-                        self.ip = target as usize;
+                    let next = if to_test != 0 {
+                        self.jump_target(isn, target)?
                     } else {
-                        self.ip += 3;
-                    }
+                        self.ip + 3
+                    };
+
+                    self.trace(isn, &[to_test, target], None, None);
+                    self.ip = next;
                 },
 
                 OP_JZ => {
                     let (to_test, target) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
                     debug!(self, "jz {} --> {}", to_test, target);
 
-                    if to_test == 0 {
-                        self.ip = target as usize;
+                    let next = if to_test == 0 {
+                        self.jump_target(isn, target)?
                     } else {
-                        self.ip += 3;
-                    }
+                        self.ip + 3
+                    };
+
+                    self.trace(isn, &[to_test, target], None, None);
+                    self.ip = next;
                 },
 
                 OP_LT => {
                     let (lhs, rhs) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
-                    let (dest, dest_i) = self.operand_output(2);
-                    *dest = (lhs < rhs) as _;
+                    let dest = self.operand_output(isn, 2)?;
+                    *self.memref(dest)? = (lhs < rhs) as _;
 
-                    debug!(self, "{} < {} --> [{}]", lhs, rhs, dest_i);
+                    debug!(self, "{} < {} --> [{}]", lhs, rhs, dest);
+                    self.trace(isn, &[lhs, rhs], Some((dest as Word, (lhs < rhs) as _)), None);
 
                     self.ip += 4;
                 },
 
                 OP_EQ => {
                     let (lhs, rhs) = (
-                        self.operand_input(0),
-                        self.operand_input(1),
+                        self.operand_input(isn, 0)?,
+                        self.operand_input(isn, 1)?,
                     );
 
-                    let (dest, dest_i) = self.operand_output(2);
-                    *dest = (lhs == rhs) as _;
+                    let dest = self.operand_output(isn, 2)?;
+                    *self.memref(dest)? = (lhs == rhs) as _;
 
-                    debug!(self, "{} == {} --> [{}]", lhs, rhs, dest_i);
+                    debug!(self, "{} == {} --> [{}]", lhs, rhs, dest);
+                    self.trace(isn, &[lhs, rhs], Some((dest as Word, (lhs == rhs) as _)), None);
 
                     self.ip += 4;
                 },
 
                 OP_RELATIVE_BASE => {
-                    let operand = self.operand_input(0);
+                    let operand = self.operand_input(isn, 0)?;
 
-                    self.relative_base += operand;
+                    self.relative_base = self.checked(isn, self.relative_base.checked_add(operand))?;
 
                     debug!(self, "relative base <-- {} (operand {})", self.relative_base, operand);
+                    self.trace(isn, &[operand], None, Some(self.relative_base));
 
                     self.ip += 2;
                 },
 
                 OP_HALT => {
                     debug!(self, "halt");
+                    self.trace(isn, &[], None, None);
                     self.state = State::Halted;
                     break;
                 }
 
                 _ => {
-                    panic!("unknown isn {}", isn);
+                    return Err(IntcodeError::UnknownOpcode { ip: self.ip, isn });
                 },
             }
+
+            if stop == Stop::Step {
+                break;
+            }
         }
 
-        output
+        Ok(())
     }
 }
 
@@ -312,13 +898,13 @@ pub fn interpret_oneshot_mutmem(
 
     let output = machine.interpret_async(inputs);
 
-    assert_eq!(mem.len(), machine.mem.len());
+    assert_eq!(mem.len(), machine.memory().len());
     for i in 0..mem.len() {
-        mem[i] = machine.mem[i];
+        mem[i] = machine.memory()[i];
     }
 
     match machine.state {
-        State::Running => panic!("oneshot failed to complete"),
+        State::Running | State::AwaitingInput => panic!("oneshot failed to complete"),
         State::Halted => output,
     }
 }
@@ -333,7 +919,7 @@ pub fn interpret_oneshot(
     let output = machine.interpret_async(inputs);
 
     match machine.state {
-        State::Running => panic!("oneshot failed to complete"),
+        State::Running | State::AwaitingInput => panic!("oneshot failed to complete"),
         State::Halted => output,
     }
 }
@@ -542,4 +1128,336 @@ mod tests {
 
         assert_eq!(output, vec![1125899906842624]);
     }
+
+    #[test]
+    fn test_error_unknown_opcode() {
+        let mut machine = IntCodeMachine::new(&[1101,1,1,5,42,0,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::UnknownOpcode { ip: 4, isn: 42 }));
+    }
+
+    #[test]
+    fn test_error_invalid_mode() {
+        let mut machine = IntCodeMachine::new(&[304,1,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::InvalidMode { ip: 0, isn: 304, operand: 0, mode: 3 }));
+    }
+
+    #[test]
+    fn test_error_immediate_write() {
+        let mut machine = IntCodeMachine::new(&[11101,1,1,5,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::ImmediateWrite { ip: 0, isn: 11101, operand: 2 }));
+    }
+
+    #[test]
+    fn test_error_negative_address() {
+        let mut machine = IntCodeMachine::new(&[109,-5,204,1,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::NegativeAddress { ip: 2, isn: 204, operand: 0, address: -4 }));
+
+        let mut machine = IntCodeMachine::new(&[1105,1,-1,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::NegativeAddress { ip: 0, isn: 1105, operand: 1, address: -1 }));
+    }
+
+    #[test]
+    fn test_error_halted() {
+        let mut machine = IntCodeMachine::new(&[99], false);
+
+        assert_eq!(machine.try_interpret_async(&mut vec![], &mut vec![]), Ok(State::Halted));
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Halted { ip: 0 }));
+    }
+
+    #[test]
+    fn test_awaiting_input() {
+        // output input + 1, forever
+        let mut machine = IntCodeMachine::new(&[3,11,101,1,11,11,4,11,1105,1,0,0], false);
+
+        assert_eq!(machine.interpret_async(&mut vec![]), vec![]);
+        assert_eq!(machine.state(), State::AwaitingInput);
+        assert!(machine.is_running());
+
+        assert_eq!(machine.interpret_async(&mut vec![4, 7]), vec![5, 8]);
+        assert_eq!(machine.state(), State::AwaitingInput);
+    }
+
+    #[test]
+    fn test_interpret_until_output() {
+        let mut machine = IntCodeMachine::new(&[104,1,104,2,3,7,99,0], false);
+
+        assert_eq!(machine.interpret_until_output(&mut vec![]), Some(1));
+        assert_eq!(machine.state(), State::Running);
+        assert_eq!(machine.interpret_until_output(&mut vec![]), Some(2));
+        assert_eq!(machine.state(), State::Running);
+        assert_eq!(machine.interpret_until_output(&mut vec![]), None);
+        assert_eq!(machine.state(), State::AwaitingInput);
+        assert_eq!(machine.interpret_until_output(&mut vec![5]), None);
+        assert_eq!(machine.state(), State::Halted);
+    }
+
+    #[test]
+    fn test_io_vecdeque() {
+        // output input * 2, forever
+        let mem = [3,11,102,2,11,11,4,11,1105,1,0,0];
+        let mut machine = IntCodeMachine::new(&mem, false);
+
+        let mut input: VecDeque<Word> = vec![1, 2, 3].into();
+        let mut output = VecDeque::new();
+        machine.interpret_io(&mut input, &mut output);
+
+        assert!(input.is_empty());
+        assert_eq!(output, vec![2, 4, 6]);
+        assert_eq!(machine.state(), State::AwaitingInput);
+    }
+
+    #[test]
+    fn test_io_iter_and_fn() {
+        let mem = [3,11,102,2,11,11,4,11,1105,1,0,0];
+        let mut machine = IntCodeMachine::new(&mem, false);
+
+        let mut total = 0;
+        machine.interpret_io(
+            &mut IterInput(1..=4),
+            &mut FnOutput(|w| total += w));
+        assert_eq!(total, 20);
+
+        let mut n = 0;
+        let output = machine.interpret_async(&mut FnInput(|| {
+            n += 1;
+            if n <= 2 { Some(n * 10) } else { None }
+        }));
+        assert_eq!(output, vec![20, 40]);
+    }
+
+    #[test]
+    fn test_io_channel() {
+        use std::sync::mpsc::channel;
+
+        let mem = [3,11,102,2,11,11,4,11,1105,1,0,0];
+        let mut machine = IntCodeMachine::new(&mem, false);
+
+        let (in_tx, mut in_rx) = channel();
+        let (mut out_tx, out_rx) = channel();
+
+        in_tx.send(5).unwrap();
+        in_tx.send(6).unwrap();
+        drop(in_tx);
+
+        machine.interpret_io(&mut in_rx, &mut out_tx);
+        assert_eq!(out_rx.try_iter().collect::<Vec<_>>(), vec![10, 12]);
+    }
+
+    #[test]
+    fn test_step() {
+        let mut machine = IntCodeMachine::new(&[109,5,3,7,204,2,99,0], false);
+        let mut output = vec![];
+
+        machine.step(&mut vec![], &mut output).unwrap();
+        assert_eq!((machine.ip(), machine.relative_base()), (2, 5));
+
+        machine.step(&mut vec![], &mut output).unwrap();
+        assert_eq!(machine.ip(), 2);
+        assert_eq!(machine.state(), State::AwaitingInput);
+
+        machine.step(&mut vec![42], &mut output).unwrap();
+        assert_eq!(machine.ip(), 4);
+        assert_eq!(machine.peek(7), 42);
+
+        machine.poke(7, 43).unwrap();
+        machine.step(&mut vec![], &mut output).unwrap();
+        machine.step(&mut vec![], &mut output).unwrap();
+        assert_eq!(output, vec![43]);
+        assert_eq!(machine.state(), State::Halted);
+    }
+
+    #[test]
+    fn test_trace() {
+        #[derive(Clone)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = Shared(Arc::new(Mutex::new(Vec::new())));
+        let mut machine = IntCodeMachine::new(&[3,15,1002,15,3,15,109,-2,1005,15,12,99,4,15,99,0], false);
+        machine.set_trace(buf.clone());
+
+        assert_eq!(machine.interpret_async(&mut vec![7]), vec![21]);
+
+        let log = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec![
+                r#"{"ip":0,"isn":3,"op":"in","operands":[],"write":[15,7]}"#,
+                r#"{"ip":2,"isn":1002,"op":"mul","operands":[7,3],"write":[15,21]}"#,
+                r#"{"ip":6,"isn":109,"op":"arb","operands":[-2],"rb":-2}"#,
+                r#"{"ip":8,"isn":1005,"op":"jnz","operands":[21,12]}"#,
+                r#"{"ip":12,"isn":4,"op":"out","operands":[21]}"#,
+                r#"{"ip":14,"isn":99,"op":"hlt","operands":[]}"#,
+            ]);
+    }
+
+    #[test]
+    fn test_snapshot_resume() {
+        // relative-base quine (day 9), so ip and relative_base both matter on resume
+        let mem = [109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99];
+        let mut machine = IntCodeMachine::new(&mem, false);
+
+        let mut first = Vec::new();
+        for _ in 0..5 {
+            first.push(machine.interpret_until_output(&mut vec![]).unwrap());
+        }
+
+        let mut snapshot = machine.snapshot();
+        snapshot.output = first.clone();
+        let snapshot = Snapshot::parse(&snapshot.to_string()).unwrap();
+        assert_eq!(snapshot.ip, machine.ip());
+        assert_eq!(snapshot.relative_base, 5);
+
+        let mut restored = IntCodeMachine::from_snapshot(&snapshot, false);
+        let mut output = snapshot.output.clone();
+        output.extend(restored.interpret_async(&mut snapshot.input.clone()));
+
+        assert_eq!(output, mem);
+        assert_eq!(restored.state(), State::Halted);
+
+        machine.restore(&snapshot);
+        assert_eq!(machine.interpret_async(&mut vec![]), &mem[5..]);
+    }
+
+    #[test]
+    fn test_snapshot_file() {
+        let mut machine = IntCodeMachine::new(&[3,7,4,7,1105,1,0,0], false);
+        assert_eq!(machine.interpret_async(&mut vec![]), vec![]);
+
+        let mut snapshot = machine.snapshot();
+        snapshot.input = vec![-3, 4];
+
+        let path = std::env::temp_dir().join(format!("intcode-snapshot-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        snapshot.save(path).unwrap();
+        let loaded = Snapshot::load(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(loaded, snapshot);
+        assert_eq!(loaded.state, State::AwaitingInput);
+
+        let mut machine = IntCodeMachine::from_snapshot(&loaded, false);
+        assert_eq!(machine.interpret_async(&mut loaded.input.clone()), vec![-3, 4]);
+
+        assert!(Snapshot::parse("intcode-snapshot 1\nstate bored\n").is_err());
+        assert!(Snapshot::parse("not a snapshot").is_err());
+    }
+
+    #[test]
+    fn test_sparse_memory() {
+        let huge = 1 << 40;
+
+        // [huge] = 5, out [huge], out [huge + 1]
+        let mem = [1101,2,3,huge,4,huge,4,huge + 1,99];
+        let mut machine = IntCodeMachine::new(&mem, false);
+        machine.set_address_limit(1 << 50);
+
+        assert_eq!(machine.interpret_async(&mut vec![]), vec![5, 0]);
+        assert_eq!(machine.memory().len(), mem.len());
+        assert_eq!(machine.peek(huge as usize), 5);
+
+        let snapshot = Snapshot::parse(&machine.snapshot().to_string()).unwrap();
+        assert_eq!(snapshot.sparse, vec![(huge as usize, 5)]);
+        assert_eq!(IntCodeMachine::from_snapshot(&snapshot, false).peek(huge as usize), 5);
+    }
+
+    #[test]
+    fn test_address_limit() {
+        let mut machine = IntCodeMachine::new(&[1101,2,3,100,99], false);
+        machine.set_address_limit(100);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::OutOfRange { ip: 0, address: 100, limit: 100 }));
+
+        // relative base into a large address, then read from it
+        let mut machine = IntCodeMachine::new(&[109,1 << 33,204,0,99], false);
+
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::OutOfRange { ip: 2, address: 1 << 33, limit: DEFAULT_ADDRESS_LIMIT }));
+
+        // running off the end into unmapped memory is still fine below the limit
+        let mut machine = IntCodeMachine::new(&[1106,0,5000], false);
+        machine.set_address_limit(5000);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::OutOfRange { ip: 5000, address: 5000, limit: 5000 }));
+    }
+
+    #[test]
+    fn test_error_overflow() {
+        let mut machine = IntCodeMachine::new(&[1101,Word::MAX,1,0,99], false);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Overflow { ip: 0, isn: 1101 }));
+
+        let mut machine = IntCodeMachine::new(&[1102,Word::MIN,-1,0,99], false);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Overflow { ip: 0, isn: 1102 }));
+
+        // the relative base itself, and an operand relative to it
+        let mut machine = IntCodeMachine::new(&[109,Word::MAX,109,1,99], false);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Overflow { ip: 2, isn: 109 }));
+        assert_eq!(machine.relative_base(), Word::MAX);
+
+        let mut machine = IntCodeMachine::new(&[109,Word::MAX,204,1,99], false);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Overflow { ip: 2, isn: 204 }));
+
+        let mut machine = IntCodeMachine::new(&[109,Word::MAX,21101,1,1,1,99], false);
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut vec![]),
+            Err(IntcodeError::Overflow { ip: 2, isn: 21101 }));
+    }
+
+    #[test]
+    fn test_output_before_error() {
+        let mut machine = IntCodeMachine::new(&[104,7,42], false);
+        let mut output = Vec::new();
+        assert_eq!(
+            machine.try_interpret_async(&mut vec![], &mut output),
+            Err(IntcodeError::UnknownOpcode { ip: 2, isn: 42 }));
+        assert_eq!(output, vec![7]);
+
+        // stops at the output, so the fault only shows on the next call
+        let mut machine = IntCodeMachine::new(&[104,7,42], false);
+        assert_eq!(machine.try_interpret_until_output(&mut vec![]), Ok(Some(7)));
+        assert_eq!(
+            machine.try_interpret_until_output(&mut vec![]),
+            Err(IntcodeError::UnknownOpcode { ip: 2, isn: 42 }));
+
+        let mut machine = IntCodeMachine::new(&[3,0,99], false);
+        assert_eq!(machine.try_interpret_async(&mut vec![], &mut vec![]), Ok(State::AwaitingInput));
+    }
 }
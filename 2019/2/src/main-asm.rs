@@ -0,0 +1,32 @@
+use std::env;
+use std::fs;
+
+mod lib;
+mod asm;
+#[cfg(test)]
+mod disasm;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect::<Vec<_>>();
+
+    let path = match &args[1..] {
+        [path] => path,
+        _ => {
+            eprintln!("Usage: {} asm-file", args[0]);
+            std::process::exit(2);
+        },
+    };
+
+    let src = fs::read_to_string(path)?;
+    let words = asm::assemble(&src)?;
+
+    println!(
+        "{}",
+        words
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(","));
+
+    Ok(())
+}
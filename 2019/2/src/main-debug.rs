@@ -0,0 +1,219 @@
+use std::convert::TryFrom;
+use std::env;
+
+mod lib;
+use lib::{IntCodeMachine, Word};
+
+mod parse;
+mod io;
+use io::{with_tty_io, flush_out_read_ch};
+
+mod disasm;
+mod debugger;
+use debugger::{Debugger, Event};
+
+const HELP: &str = "\
+s  step
+c  continue
+b  toggle breakpoint
+w  toggle watchpoint
+r  registers
+m  show memory
+p  poke memory
+i  supply input
+o  show output
+q  quit";
+
+// we're in -echo -icanon mode, so handle echo and backspace ourselves
+fn read_line(prompt: &str) -> String {
+    print!("{}", prompt);
+
+    let mut line = String::new();
+    loop {
+        match flush_out_read_ch() {
+            '\n' => {
+                println!();
+                break line;
+            },
+            '\x7f' | '\x08' => {
+                if line.pop().is_some() {
+                    print!("\x08 \x08");
+                }
+            },
+            c => {
+                print!("{}", c);
+                line.push(c);
+            },
+        }
+    }
+}
+
+fn read_words(prompt: &str) -> Vec<Word> {
+    read_line(prompt)
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| match s.parse() {
+            Ok(w) => Some(w),
+            Err(_) => {
+                println!("ignoring \"{}\"", s);
+                None
+            },
+        })
+        .collect()
+}
+
+// None (and says so) if `w` can't be an address
+fn address(w: Word) -> Option<usize> {
+    let addr = usize::try_from(w).ok();
+    if addr.is_none() {
+        println!("ignoring negative address {}", w);
+    }
+    addr
+}
+
+fn show_isn(dbg: &Debugger) {
+    let ip = dbg.machine.ip();
+    let mem = dbg.machine.memory();
+
+    let isn = if ip < mem.len() {
+        // an instruction is at most 4 words
+        disasm::disassemble(&mem[..mem.len().min(ip + 4)], ip)
+            .to_string()
+            .lines()
+            .find(|l| !l.ends_with(':'))
+            .map(|l| l.split(';').next().unwrap().trim().to_string())
+    } else {
+        None
+    };
+
+    println!("{:>5}: {}", ip, isn.unwrap_or_else(|| "<past end of memory>".into()));
+}
+
+fn show_event(dbg: &Debugger, event: &Event) {
+    match event {
+        Event::Stepped => {},
+        Event::Breakpoint(ip) => println!("breakpoint at {}", ip),
+        Event::Watchpoint { addr, old, new } => println!("watchpoint [{}]: {} --> {}", addr, old, new),
+        Event::AwaitingInput => println!("awaiting input"),
+        Event::Halted => println!("halted"),
+    }
+    show_isn(dbg);
+}
+
+fn run(dbg: &mut Debugger) {
+    let mut shown_output = 0;
+
+    show_isn(dbg);
+
+    loop {
+        print!("(icdb) ");
+        let cmd = flush_out_read_ch();
+        println!("{}", cmd);
+
+        let result = match cmd {
+            's' => Some(dbg.step()),
+            'c' => Some(dbg.cont()),
+            'b' => {
+                for ip in read_words("breakpoint ip: ").into_iter().filter_map(address) {
+                    let set = dbg.toggle_breakpoint(ip);
+                    println!("breakpoint {} {}", ip, if set { "set" } else { "cleared" });
+                }
+                None
+            },
+            'w' => {
+                for addr in read_words("watch address: ").into_iter().filter_map(address) {
+                    let set = dbg.toggle_watchpoint(addr);
+                    println!("watchpoint {} {}", addr, if set { "set" } else { "cleared" });
+                }
+                None
+            },
+            'r' => {
+                println!(
+                    "ip {} relative_base {} state {:?} pending input {:?}",
+                    dbg.machine.ip(),
+                    dbg.machine.relative_base(),
+                    dbg.machine.state(),
+                    dbg.input);
+                println!("breakpoints {:?} watchpoints {:?}", dbg.breakpoints, dbg.watchpoints);
+                None
+            },
+            'm' => {
+                let range = match read_words("start [len]: ")[..] {
+                    [start] => Some((start, 1)),
+                    [start, len] => Some((start, len)),
+                    _ => None,
+                };
+
+                match range.map(|(start, len)| (usize::try_from(start), usize::try_from(len))) {
+                    Some((Ok(start), Ok(len))) => match dbg.memory_range(start, len) {
+                        Some(words) => println!("{:?}", words),
+                        None => println!("{} words from {} runs off the end of memory", len, start),
+                    },
+                    Some(_) => println!("start and len can't be negative"),
+                    None => println!("expected start [len]"),
+                }
+                None
+            },
+            'p' => {
+                match read_words("address value: ")[..] {
+                    [addr, value] => if let Some(addr) = address(addr) {
+                        if let Err(e) = dbg.machine.poke(addr, value) {
+                            println!("{}", e);
+                        }
+                    },
+                    _ => println!("expected address value"),
+                }
+                None
+            },
+            'i' => {
+                dbg.input.extend(read_words("input: "));
+                None
+            },
+            'o' => {
+                println!("{:?}", dbg.output);
+                None
+            },
+            'q' => break,
+            _ => {
+                println!("{}", HELP);
+                None
+            },
+        };
+
+        match result {
+            Some(Ok(event)) => {
+                for w in &dbg.output[shown_output..] {
+                    println!("output: {}", w);
+                }
+                shown_output = dbg.output.len();
+
+                show_event(dbg, &event);
+
+                if event == Event::AwaitingInput {
+                    dbg.input.extend(read_words("input: "));
+                }
+            },
+            Some(Err(e)) => println!("fault: {}", e),
+            None => {},
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect::<Vec<_>>();
+
+    let path = match &args[1..] {
+        [path] => path,
+        _ => {
+            eprintln!("Usage: {} intcode-file", args[0]);
+            std::process::exit(2);
+        },
+    };
+
+    let bytes = parse::bytes(path)?;
+    let mut dbg = Debugger::new(IntCodeMachine::new(&bytes, false));
+
+    with_tty_io(|| run(&mut dbg));
+
+    Ok(())
+}
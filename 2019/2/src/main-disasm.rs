@@ -0,0 +1,24 @@
+use std::env;
+
+mod lib;
+mod parse;
+mod disasm;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect::<Vec<_>>();
+
+    let (path, start) = match &args[1..] {
+        [path] => (path, 0),
+        [path, start] => (path, start.parse()?),
+        _ => {
+            eprintln!("Usage: {} intcode-file [start-address]", args[0]);
+            std::process::exit(2);
+        },
+    };
+
+    let bytes = parse::bytes(path)?;
+
+    print!("{}", disasm::disassemble(&bytes, start));
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::io::{self, BufRead};
+
+mod lib;
+use lib::{IntCodeMachine, State};
+
+mod parse;
+
+mod d2;
+mod grid;
+use grid::Grid;
+
+mod render;
+use render::{apply_triples, Renderer};
+
+// runs any program which outputs (x, y, tile) triples, showing the raw tile ids. when the
+// program wants input, a line of comma-separated words is read from stdin
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().collect::<Vec<_>>();
+    let mut renderer = Renderer::stdout();
+
+    loop {
+        match args.get(1).map(String::as_str) {
+            Some("-n") => {
+                renderer.set_colour(false);
+                args.remove(1);
+            },
+            Some("-d") if args.len() > 2 => {
+                renderer.set_dump(&args[2]);
+                args.drain(1..3);
+            },
+            _ => break,
+        }
+    }
+
+    if args.len() < 2 {
+        eprintln!("Usage: {} [-n] [-d dump-prefix] intcode-file [input...]", args[0]);
+        std::process::exit(2);
+    }
+
+    let bytes = parse::bytes(&args[1])?;
+    let mut input = args[2..]
+        .iter()
+        .map(|s| s.parse())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut machine = IntCodeMachine::new(&bytes, false);
+    let mut grid = Grid::new_default(0);
+    let mut others = Vec::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        // draw whatever made it out before any fault
+        let mut output = Vec::new();
+        let result = machine.try_interpret_async(&mut input, &mut output);
+        others.extend(apply_triples(&mut grid, &output, |c, t| {
+            if c.x < 0 || c.y < 0 { None } else { Some(t) }
+        }));
+
+        let status = others
+            .last()
+            .map(|(c, w)| format!("({},{}) = {}", c.x, c.y, w))
+            .unwrap_or_default();
+        renderer.draw(&grid, &status)?;
+        result?;
+
+        if machine.state() == State::Halted {
+            break;
+        }
+
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        for w in line.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            input.push(w.parse()?);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use std::env;
+
+mod lib;
+use lib::IntCodeMachine;
+
+mod parse;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().collect::<Vec<_>>();
+
+    if args.len() < 3 {
+        eprintln!("Usage: {} intcode-file trace-file [input...]", args[0]);
+        std::process::exit(2);
+    }
+
+    let bytes = parse::bytes(&args[1])?;
+    let mut input = args[3..]
+        .iter()
+        .map(|s| s.parse())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut machine = IntCodeMachine::new(&bytes, false);
+    machine.trace_to_file(&args[2])?;
+
+    let mut output = Vec::new();
+    let result = machine.try_interpret_async(&mut input, &mut output);
+    machine.flush_trace()?;
+
+    println!("{:?}", output);
+    result?;
+    eprintln!("state: {:?}", machine.state());
+
+    Ok(())
+}
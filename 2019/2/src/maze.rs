@@ -0,0 +1,264 @@
+#![allow(dead_code)]
+
+use std::collections::{hash_map::Entry, HashMap, VecDeque};
+use std::fmt;
+
+use crate::d2::{Compass, Coord};
+use crate::grid::Grid;
+use crate::lib::{IntCodeMachine, IntcodeError, Word};
+
+// maps a day 15 style maze: the droid is sent a direction (1-4: N, S, W, E) and replies 0 if
+// it hit a wall, 1 if it moved, or 2 if it moved and found what we're looking for. exploring
+// is depth first, backtracking along the way we came, so the droid ends up back at the start
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GridEnt {
+    Wall,
+    Ok,
+    Found,
+    Unknown,
+    Droid,
+}
+
+impl From<Word> for GridEnt {
+    fn from(e: Word) -> GridEnt {
+        match e {
+            0 => GridEnt::Wall,
+            1 => GridEnt::Ok,
+            2 => GridEnt::Found,
+            _ => panic!("can't convert {} to grident", e),
+        }
+    }
+}
+
+pub fn command(dir: Compass) -> Word {
+    match dir {
+        Compass::North => 1,
+        Compass::South => 2,
+        Compass::West => 3,
+        Compass::East => 4,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MazeError(pub String);
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for MazeError {}
+
+impl From<IntcodeError> for MazeError {
+    fn from(e: IntcodeError) -> Self {
+        MazeError(e.to_string())
+    }
+}
+
+// tries to move one square, returning what's there - the droid stays put if it's a wall
+pub trait Droid {
+    fn try_move(&mut self, dir: Compass) -> Result<GridEnt, MazeError>;
+}
+
+impl Droid for IntCodeMachine {
+    fn try_move(&mut self, dir: Compass) -> Result<GridEnt, MazeError> {
+        let mut output = Vec::new();
+        self.try_interpret_async(&mut vec![command(dir)], &mut output)?;
+
+        match output[..] {
+            [r @ 0..=2] => Ok(r.into()),
+            _ => Err(MazeError(format!("expected a single 0, 1 or 2, got {:?}", output))),
+        }
+    }
+}
+
+// walks every reachable square, returning the map with the start at the origin
+pub fn explore<D: Droid>(droid: &mut D) -> Result<Grid<GridEnt>, MazeError> {
+    let mut grid = Grid::new_default(GridEnt::Unknown);
+    grid.map.insert(Coord::zero(), GridEnt::Ok);
+
+    // how we got to each square on the current path, and which direction to try next
+    let mut path: Vec<(Coord, Option<Compass>, usize)> = vec![(Coord::zero(), None, 0)];
+
+    while let Some((at, came, next)) = path.last_mut() {
+        let at = *at;
+
+        if *next == Compass::ALL.len() {
+            if let Some(came) = *came {
+                if droid.try_move(came.opposite())? == GridEnt::Wall {
+                    return Err(MazeError(format!("couldn't backtrack from {:?}", at)));
+                }
+            }
+            path.pop();
+            continue;
+        }
+
+        let dir = Compass::ALL[*next];
+        *next += 1;
+
+        let to = at + dir;
+        if grid.map.contains_key(&to) {
+            continue;
+        }
+
+        let ent = droid.try_move(dir)?;
+        grid.map.insert(to, ent);
+
+        if ent != GridEnt::Wall {
+            path.push((to, Some(dir), 0));
+        }
+    }
+
+    Ok(grid)
+}
+
+pub fn find(grid: &Grid<GridEnt>, ent: GridEnt) -> Option<Coord> {
+    grid.map
+        .iter()
+        .find(|&(_, &e)| e == ent)
+        .map(|(&c, _)| c)
+}
+
+// squares reachable from `from`, grouped by distance
+pub fn spread(grid: &Grid<GridEnt>, from: Coord) -> Vec<Vec<Coord>> {
+    let mut seen = HashMap::new();
+    let mut steps = vec![];
+    let mut queue = VecDeque::new();
+
+    seen.insert(from, 0);
+    queue.push_back(from);
+
+    while let Some(at) = queue.pop_front() {
+        let dist = seen[&at];
+        if steps.len() == dist {
+            steps.push(vec![]);
+        }
+        steps[dist].push(at);
+
+        for &dir in &Compass::ALL {
+            let to = at + dir;
+
+            match grid.map.get(&to) {
+                Some(GridEnt::Ok) | Some(GridEnt::Found) => {},
+                _ => continue,
+            }
+
+            if let Entry::Vacant(e) = seen.entry(to) {
+                e.insert(dist + 1);
+                queue.push_back(to);
+            }
+        }
+    }
+
+    steps
+}
+
+pub fn shortest_path(grid: &Grid<GridEnt>, from: Coord, to: Coord) -> Option<usize> {
+    spread(grid, from)
+        .iter()
+        .position(|step| step.contains(&to))
+}
+
+// minutes for oxygen to fill the maze from `from`, calling `tick` with each minute's newly
+// filled squares
+pub fn oxygen_fill<F>(grid: &Grid<GridEnt>, from: Coord, mut tick: F) -> usize
+    where F: FnMut(usize, &[Coord])
+{
+    let steps = spread(grid, from);
+
+    for (minute, filled) in steps.iter().enumerate() {
+        tick(minute, filled);
+    }
+
+    steps.len().saturating_sub(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a maze drawn as text, `S` being where the droid starts and `O` the oxygen system
+    struct Fake {
+        walls: Grid<char>,
+        at: Coord,
+        steps: usize,
+    }
+
+    impl Fake {
+        fn new(maze: &str) -> Self {
+            let mut walls = Grid::new_default('#');
+            let mut at = Coord::zero();
+
+            for (y, line) in maze.lines().enumerate() {
+                for (x, ch) in line.chars().enumerate() {
+                    let c = Coord::new(x as isize, y as isize);
+                    if ch == 'S' {
+                        at = c;
+                    }
+                    walls.map.insert(c, ch);
+                }
+            }
+
+            Self { walls, at, steps: 0 }
+        }
+    }
+
+    impl Droid for Fake {
+        fn try_move(&mut self, dir: Compass) -> Result<GridEnt, MazeError> {
+            self.steps += 1;
+            let to = self.at + dir;
+
+            let ent = match self.walls.get_default(&to) {
+                '.' | 'S' => GridEnt::Ok,
+                'O' => GridEnt::Found,
+                _ => return Ok(GridEnt::Wall),
+            };
+            self.at = to;
+
+            Ok(ent)
+        }
+    }
+
+    // the oxygen example from the puzzle
+    const MAZE: &str = "\
+ ##
+#..##
+#.#..#
+#.O.#
+ #S#
+";
+
+    #[test]
+    fn test_explore() {
+        let mut droid = Fake::new(MAZE);
+        let start = droid.at;
+
+        let grid = explore(&mut droid).unwrap();
+        assert_eq!(droid.at, start);
+
+        let oxygen = find(&grid, GridEnt::Found).unwrap();
+        assert_eq!(oxygen, Coord::new(0, -1));
+        assert_eq!(shortest_path(&grid, Coord::zero(), oxygen), Some(1));
+
+        let open = grid.map.values().filter(|&&e| e != GridEnt::Wall).count();
+        assert_eq!(open, 9);
+
+        let mut ticks = vec![];
+        let minutes = oxygen_fill(&grid, oxygen, |m, filled| ticks.push((m, filled.len())));
+        assert_eq!(minutes, 4);
+        assert_eq!(ticks, vec![(0, 1), (1, 3), (2, 2), (3, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let mut droid = Fake::new("#S#\n###\n#O#\n");
+
+        let grid = explore(&mut droid).unwrap();
+        assert_eq!(droid.steps, 4);
+        assert_eq!(find(&grid, GridEnt::Found), None);
+        assert_eq!(shortest_path(&grid, Coord::zero(), Coord::new(0, 2)), None);
+        assert_eq!(oxygen_fill(&grid, Coord::zero(), |_, _| {}), 0);
+    }
+}
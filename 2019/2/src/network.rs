@@ -0,0 +1,384 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+use std::io::Write;
+
+use crate::lib::{IntCodeMachine, IntcodeError, State, Word};
+
+// N machines on addresses 0..N, sending each other (dest, x, y) packets. a machine with
+// nothing queued reads -1. packets for the NAT's address go to the `Nat`, which is also
+// told whenever the network goes idle
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Packet {
+    pub src: Word,
+    pub dest: Word,
+    pub x: Word,
+    pub y: Word,
+}
+
+pub enum NatAction<T> {
+    Wait,
+    Send(Packet), // `src` is filled in by the network
+    Stop(T),
+}
+
+pub trait Nat {
+    type Output;
+
+    fn receive(&mut self, packet: Packet) -> NatAction<Self::Output>;
+    fn idle(&mut self) -> NatAction<Self::Output>;
+}
+
+// stops the network with the first packet it's sent
+pub struct FirstPacket;
+
+impl Nat for FirstPacket {
+    type Output = Packet;
+
+    fn receive(&mut self, packet: Packet) -> NatAction<Packet> {
+        NatAction::Stop(packet)
+    }
+
+    fn idle(&mut self) -> NatAction<Packet> {
+        NatAction::Wait
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Schedule {
+    // every machine runs in address order, each round
+    RoundRobin,
+    // only machines with packets waiting run, in the order the packets arrived. once
+    // everything's drained, every machine is polled once (reading -1)
+    EventDriven,
+}
+
+struct Node {
+    machine: IntCodeMachine,
+    queue: VecDeque<Word>,
+    output: Vec<Word>, // may hold a partial packet between runs
+}
+
+pub struct Network<N: Nat> {
+    nodes: Vec<Node>,
+    nat: N,
+    nat_addr: Word,
+    schedule: Schedule,
+    idle_rounds: usize,
+    round: usize,
+    trace: Option<Box<dyn Write>>,
+}
+
+pub const DEFAULT_NAT_ADDRESS: Word = 255;
+
+impl<N: Nat> Network<N> {
+    // every machine is booted with its own address as its first input
+    pub fn new(mem: &[Word], size: usize, nat: N) -> Self {
+        let nodes = (0..size)
+            .map(|addr| Node {
+                machine: IntCodeMachine::new(mem, false),
+                queue: VecDeque::from(vec![addr as Word]),
+                output: Vec::new(),
+            })
+            .collect();
+
+        Self {
+            nodes,
+            nat,
+            nat_addr: DEFAULT_NAT_ADDRESS,
+            schedule: Schedule::RoundRobin,
+            idle_rounds: 1,
+            round: 0,
+            trace: None,
+        }
+    }
+
+    pub fn set_nat_address(&mut self, addr: Word) {
+        self.nat_addr = addr;
+    }
+
+    pub fn set_schedule(&mut self, schedule: Schedule) {
+        self.schedule = schedule;
+    }
+
+    // how many consecutive rounds without a packet being sent or received count as idle
+    pub fn set_idle_rounds(&mut self, rounds: usize) {
+        self.idle_rounds = rounds.max(1);
+    }
+
+    // one JSON object per packet routed
+    pub fn set_trace<W: Write + 'static>(&mut self, w: W) {
+        self.trace = Some(Box::new(w));
+    }
+
+    pub fn nat(&self) -> &N {
+        &self.nat
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.round
+    }
+
+    // runs until the NAT stops us. None if every machine halts, or the network is idle and the
+    // NAT has nothing to send
+    pub fn run(&mut self) -> Result<Option<N::Output>, IntcodeError> {
+        let mut ready = match self.schedule {
+            Schedule::RoundRobin => VecDeque::new(),
+            Schedule::EventDriven => (0..self.nodes.len()).collect(),
+        };
+        let mut idle = 0;
+
+        loop {
+            let mut busy = false;
+
+            if let Some(addr) = ready.pop_front() {
+                // event-driven: service whoever has packets first, idle or not
+                if let Some(out) = self.service(addr, &mut busy, &mut ready)? {
+                    return Ok(Some(out));
+                }
+                continue;
+            }
+
+            self.round += 1;
+            for addr in 0..self.nodes.len() {
+                if let Some(out) = self.service(addr, &mut busy, &mut ready)? {
+                    return Ok(Some(out));
+                }
+            }
+
+            if self.nodes.iter().all(|node| node.machine.state() == State::Halted) {
+                return Ok(None);
+            }
+
+            if busy || !ready.is_empty() {
+                idle = 0;
+                continue;
+            }
+
+            idle += 1;
+            if idle < self.idle_rounds {
+                continue;
+            }
+            idle = 0;
+
+            match self.nat.idle() {
+                NatAction::Wait => return Ok(None),
+                NatAction::Send(packet) => {
+                    if let Some(out) = self.route(Packet { src: self.nat_addr, ..packet }, &mut ready) {
+                        return Ok(Some(out));
+                    }
+                },
+                NatAction::Stop(out) => return Ok(Some(out)),
+            }
+        }
+    }
+
+    // runs a single machine until it blocks, then routes whatever it sent
+    fn service(
+        &mut self,
+        addr: usize,
+        busy: &mut bool,
+        ready: &mut VecDeque<usize>,
+    ) -> Result<Option<N::Output>, IntcodeError> {
+        let node = &mut self.nodes[addr];
+        if node.machine.state() == State::Halted {
+            return Ok(None);
+        }
+
+        if node.queue.is_empty() {
+            node.queue.push_back(-1);
+        } else {
+            *busy = true;
+        }
+
+        node.machine.try_interpret_io(&mut node.queue, &mut node.output)?;
+
+        let sent = node.output.len() / 3 * 3;
+        let packets = node.output
+            .drain(..sent)
+            .collect::<Vec<_>>();
+
+        for packet in packets.chunks(3) {
+            *busy = true;
+
+            let packet = Packet {
+                src: addr as Word,
+                dest: packet[0],
+                x: packet[1],
+                y: packet[2],
+            };
+
+            if let Some(out) = self.route(packet, ready) {
+                return Ok(Some(out));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn route(&mut self, packet: Packet, ready: &mut VecDeque<usize>) -> Option<N::Output> {
+        self.trace_packet(&packet);
+
+        if packet.dest == self.nat_addr {
+            return match self.nat.receive(packet) {
+                NatAction::Wait => None,
+                NatAction::Send(reply) => self.route(Packet { src: self.nat_addr, ..reply }, ready),
+                NatAction::Stop(out) => Some(out),
+            };
+        }
+
+        if let Some(node) = self.nodes.get_mut(packet.dest as usize).filter(|_| packet.dest >= 0) {
+            node.queue.push_back(packet.x);
+            node.queue.push_back(packet.y);
+
+            let dest = packet.dest as usize;
+            if self.schedule == Schedule::EventDriven && !ready.contains(&dest) {
+                ready.push_back(dest);
+            }
+        }
+        // else dropped, but still traced
+
+        None
+    }
+
+    fn trace_packet(&mut self, packet: &Packet) {
+        let round = self.round;
+
+        if let Some(w) = &mut self.trace {
+            let _ = writeln!(
+                w,
+                "{{\"round\":{},\"src\":{},\"dest\":{},\"x\":{},\"y\":{}}}",
+                round,
+                packet.src,
+                packet.dest,
+                packet.x,
+                packet.y);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // reads its address, then for each packet (x, y) received sends (x, y + addr) onward to
+    // address x. a -1 poll is ignored
+    const RELAY: [Word; 30] = [
+        3,29,           //  0: in [addr]
+        3,26,           //  2: in [x]
+        1008,26,-1,28,  //  4: [t] = x == -1
+        1005,28,2,      //  8: jnz [t], #2
+        3,27,           // 11: in [y]
+        1,27,29,27,     // 13: [y] += [addr]
+        4,26,           // 17: out [x]    (dest)
+        4,26,           // 19: out [x]
+        4,27,           // 21: out [y]
+        1105,1,2,       // 23: jnz #1, #2
+        0,0,0,0,        // 26: x, y, t, addr
+    ];
+
+    struct Collect(Vec<Packet>);
+
+    impl Nat for Collect {
+        type Output = usize;
+
+        fn receive(&mut self, packet: Packet) -> NatAction<usize> {
+            self.0.push(packet);
+            NatAction::Wait
+        }
+
+        // kick address 1 three times, then stop with how many packets came back
+        fn idle(&mut self) -> NatAction<usize> {
+            if self.0.len() >= 3 {
+                return NatAction::Stop(self.0.len());
+            }
+            NatAction::Send(Packet { src: 0, dest: 1, x: 5, y: 0 })
+        }
+    }
+
+    #[derive(Clone)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_first_packet() {
+        let mut net = Network::new(&RELAY, 3, FirstPacket);
+
+        // nothing is sent unless the NAT kicks things off
+        assert_eq!(net.run(), Ok(None));
+
+        // every node sends (addr, addr + 100) to the NAT, then just polls
+        let to_nat = [
+            3,17,           //  0: in [addr]
+            1001,17,100,18, //  2: [y] = [addr] + 100
+            104,255,        //  6: out #255
+            4,17,           //  8: out [addr]
+            4,18,           // 10: out [y]
+            3,19,           // 12: in [t]
+            1105,1,12,      // 14: jnz #1, #12
+            0,0,0,          // 17: addr, y, t
+        ];
+
+        for &schedule in &[Schedule::RoundRobin, Schedule::EventDriven] {
+            let mut net = Network::new(&to_nat, 3, FirstPacket);
+            net.set_schedule(schedule);
+
+            // node 0 runs first, and the network stops as soon as its packet arrives
+            assert_eq!(
+                net.run(),
+                Ok(Some(Packet { src: 0, dest: DEFAULT_NAT_ADDRESS, x: 0, y: 100 })));
+        }
+    }
+
+    #[test]
+    fn test_nat_and_trace() {
+        for &schedule in &[Schedule::RoundRobin, Schedule::EventDriven] {
+            let mut net = Network::new(&RELAY, 3, Collect(vec![]));
+            net.set_nat_address(5);
+            net.set_schedule(schedule);
+
+            let buf = SharedBuf(Arc::new(Mutex::new(Vec::new())));
+            net.set_trace(buf.clone());
+
+            assert_eq!(net.run(), Ok(Some(3)));
+
+            // nat -> 1 -> nat, with 1 adding its address to y each time
+            assert_eq!(
+                net.nat().0,
+                vec![
+                    Packet { src: 1, dest: 5, x: 5, y: 1 },
+                    Packet { src: 1, dest: 5, x: 5, y: 1 },
+                    Packet { src: 1, dest: 5, x: 5, y: 1 },
+                ]);
+
+            let trace = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+            let lines = trace.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), 6);
+            assert!(lines[0].ends_with("\"src\":5,\"dest\":1,\"x\":5,\"y\":0}"));
+            assert!(lines[1].ends_with("\"src\":1,\"dest\":5,\"x\":5,\"y\":1}"));
+        }
+    }
+
+    #[test]
+    fn test_idle_rounds() {
+        let mut net = Network::new(&RELAY, 2, Collect(vec![]));
+        net.set_nat_address(5);
+        net.set_idle_rounds(4);
+
+        assert_eq!(net.run(), Ok(Some(3)));
+        // boot round, then 4 idle rounds before each of the NAT's 3 kicks and the final stop,
+        // plus a round to deliver each kick
+        assert_eq!(net.rounds(), 1 + 4 * 4 + 3);
+    }
+}
@@ -0,0 +1,167 @@
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use itertools::Itertools;
+
+use crate::lib::{IntCodeMachine, IntcodeError, Word};
+
+// machines chained output -> input. with feedback, the last stage's output also goes back
+// into the first stage. each stage can be given initial inputs (e.g. a phase setting) which
+// it reads before anything from the previous stage
+
+struct Stage {
+    machine: IntCodeMachine,
+    queue: VecDeque<Word>,
+}
+
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    feedback: bool,
+    output: Vec<Word>,
+}
+
+impl Pipeline {
+    pub fn new(mem: &[Word], stages: usize) -> Self {
+        Self::from_machines((0..stages).map(|_| IntCodeMachine::new(mem, false)).collect())
+    }
+
+    pub fn from_machines(machines: Vec<IntCodeMachine>) -> Self {
+        let stages = machines
+            .into_iter()
+            .map(|machine| Stage { machine, queue: VecDeque::new() })
+            .collect();
+
+        Self {
+            stages,
+            feedback: false,
+            output: Vec::new(),
+        }
+    }
+
+    pub fn set_feedback(&mut self, feedback: bool) {
+        self.feedback = feedback;
+    }
+
+    pub fn push_input(&mut self, stage: usize, input: &[Word]) {
+        self.stages[stage].queue.extend(input);
+    }
+
+    // one input per stage, e.g. day 7's phase settings
+    pub fn push_inputs(&mut self, inputs: &[Word]) {
+        for (stage, &input) in self.stages.iter_mut().zip(inputs) {
+            stage.queue.push_back(input);
+        }
+    }
+
+    // everything the last stage has output, including anything fed back
+    pub fn output(&self) -> &[Word] {
+        &self.output
+    }
+
+    // runs stages in order until they've all halted, or none of them can make progress. with
+    // no stages there's nothing to run, and no output
+    pub fn run(&mut self) -> Result<&[Word], IntcodeError> {
+        let last = match self.stages.len().checked_sub(1) {
+            Some(last) => last,
+            None => return Ok(&self.output),
+        };
+
+        loop {
+            let mut progress = false;
+
+            for i in 0..self.stages.len() {
+                let stage = &mut self.stages[i];
+                if !stage.machine.is_running() {
+                    continue;
+                }
+
+                let mut output = Vec::new();
+                stage.machine.try_interpret_io(&mut stage.queue, &mut output)?;
+
+                progress |= !output.is_empty() || !stage.machine.is_running();
+
+                if i == last {
+                    self.output.extend(&output);
+                    if self.feedback {
+                        self.stages[0].queue.extend(output);
+                    }
+                } else {
+                    self.stages[i + 1].queue.extend(output);
+                }
+            }
+
+            if !progress {
+                break;
+            }
+        }
+
+        Ok(&self.output)
+    }
+}
+
+// tries every ordering of `items`, returning the one with the highest score (the last, if
+// there's a tie)
+pub fn best_permutation<F, E>(
+    items: &[Word],
+    mut score: F,
+) -> Result<Option<(Word, Vec<Word>)>, E>
+    where F: FnMut(&[Word]) -> Result<Word, E>
+{
+    let mut best = None;
+
+    for perm in items.iter().cloned().permutations(items.len()) {
+        let s = score(&perm)?;
+
+        match best {
+            Some((max, _)) if s < max => {},
+            _ => best = Some((s, perm)),
+        }
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_inputs() {
+        // outputs each input doubled
+        let double = [3,11,1002,11,2,11,4,11,1105,1,0,0];
+
+        let mut pipeline = Pipeline::new(&double, 3);
+        pipeline.push_input(0, &[1, 5]);
+
+        assert_eq!(pipeline.run(), Ok(&[8, 40][..]));
+    }
+
+    #[test]
+    fn test_feedback() {
+        // outputs each input + 1, halting after it's been given 10
+        let inc = [3,16,1008,16,10,17,1001,16,1,16,4,16,1006,17,0,99,0,0];
+
+        let mut pipeline = Pipeline::new(&inc, 2);
+        pipeline.set_feedback(true);
+        pipeline.push_input(0, &[0]);
+
+        assert_eq!(pipeline.run(), Ok(&[2, 4, 6, 8, 10, 12][..]));
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut pipeline = Pipeline::new(&[99], 0);
+        pipeline.set_feedback(true);
+        pipeline.push_inputs(&[1, 2]);
+
+        assert_eq!(pipeline.run(), Ok(&[][..]));
+    }
+
+    #[test]
+    fn test_best_permutation() {
+        let score = |p: &[Word]| Ok::<_, IntcodeError>(p[0] * 100 + p[1] * 10 + p[2]);
+
+        assert_eq!(best_permutation(&[1, 2, 3], score), Ok(Some((321, vec![3, 2, 1]))));
+    }
+}
@@ -0,0 +1,294 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+
+use crate::d2::Coord;
+use crate::grid::Grid;
+use crate::lib::Word;
+
+// draws a `Grid` to a terminal: a status line, then the grid. the first frame (and any
+// frame where the grid's bounds change) clears the screen, after that only cells which
+// have changed are repainted
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Colour {
+    Red = 31,
+    Green = 32,
+    Yellow = 33,
+    Blue = 34,
+    Magenta = 35,
+    Cyan = 36,
+    White = 37,
+}
+
+pub trait Glyph {
+    fn glyph(&self) -> char;
+
+    fn colour(&self) -> Option<Colour> {
+        None
+    }
+}
+
+// raw tile ids, for programs we don't have a mapping for
+impl Glyph for Word {
+    fn glyph(&self) -> char {
+        match *self {
+            0 => ' ',
+            n if n > 0 => std::char::from_digit(n as u32, 36).unwrap_or('?'),
+            _ => '?',
+        }
+    }
+}
+
+type Cell = (char, Option<Colour>);
+
+const CLEAR: &str = "\x1b[2J\x1b[H";
+const CLEAR_EOL: &str = "\x1b[0K";
+
+pub struct Renderer<W: Write> {
+    out: W,
+    colour: bool,
+    dump: Option<String>,
+    frame: usize,
+
+    // what's currently on screen
+    bounds: Option<(Coord, Coord)>,
+    status: String,
+    cells: HashMap<Coord, Cell>,
+}
+
+impl Renderer<io::Stdout> {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> Renderer<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            colour: true,
+            dump: None,
+            frame: 0,
+            bounds: None,
+            status: String::new(),
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn set_colour(&mut self, colour: bool) {
+        self.colour = colour;
+        self.bounds = None; // repaint everything
+    }
+
+    // every frame is also written, without colour, to `<prefix>-NNNNNN.txt`
+    pub fn set_dump(&mut self, prefix: &str) {
+        self.dump = Some(prefix.into());
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frame
+    }
+
+    pub fn draw<T: Glyph>(&mut self, grid: &Grid<T>, status: &str) -> io::Result<()> {
+        let (min, max) = grid.minmax();
+
+        let blank = grid.default
+            .as_ref()
+            .map(|d| (d.glyph(), d.colour()))
+            .unwrap_or((' ', None));
+
+        let cell_at = |c: &Coord| grid.map
+            .get(c)
+            .map(|t| (t.glyph(), t.colour()))
+            .unwrap_or(blank);
+
+        if self.bounds != Some((min, max)) {
+            write!(self.out, "{}{}{}", CLEAR, status, CLEAR_EOL)?;
+            self.cells.clear();
+
+            for y in min.y..=max.y {
+                writeln!(self.out)?;
+                for x in min.x..=max.x {
+                    let c = Coord::new(x, y);
+                    let cell = cell_at(&c);
+
+                    self.write_cell(cell)?;
+                    self.cells.insert(c, cell);
+                }
+            }
+
+            self.bounds = Some((min, max));
+        } else {
+            if status != self.status {
+                write!(self.out, "\x1b[1;1H{}{}", status, CLEAR_EOL)?;
+            }
+
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let c = Coord::new(x, y);
+                    let cell = cell_at(&c);
+
+                    if self.cells.get(&c) != Some(&cell) {
+                        // 1-based, and the status line takes the first row
+                        write!(self.out, "\x1b[{};{}H", y - min.y + 2, x - min.x + 1)?;
+                        self.write_cell(cell)?;
+                        self.cells.insert(c, cell);
+                    }
+                }
+            }
+        }
+
+        // leave the cursor below the grid
+        write!(self.out, "\x1b[{};1H", max.y - min.y + 3)?;
+        self.out.flush()?;
+
+        self.status = status.into();
+
+        if let Some(prefix) = &self.dump {
+            let mut text = String::from(status);
+            text.push('\n');
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    text.push(cell_at(&Coord::new(x, y)).0);
+                }
+                text.push('\n');
+            }
+
+            fs::write(format!("{}-{:06}.txt", prefix, self.frame), text)?;
+        }
+
+        self.frame += 1;
+
+        Ok(())
+    }
+
+    fn write_cell(&mut self, (ch, colour): Cell) -> io::Result<()> {
+        match colour {
+            Some(colour) if self.colour => write!(self.out, "\x1b[{}m{}\x1b[0m", colour as u8, ch),
+            _ => write!(self.out, "{}", ch),
+        }
+    }
+}
+
+// applies an intcode program's (x, y, tile) output triples to `grid`. `tile` returns None for
+// triples which aren't tiles (e.g. day 13's score), and these are returned instead. any
+// trailing partial triple is ignored
+pub fn apply_triples<T, F>(grid: &mut Grid<T>, output: &[Word], mut tile: F) -> Vec<(Coord, Word)>
+    where F: FnMut(Coord, Word) -> Option<T>
+{
+    let mut rest = Vec::new();
+
+    for triple in output.chunks_exact(3) {
+        let at = Coord::new(triple[0] as isize, triple[1] as isize);
+
+        match tile(at, triple[2]) {
+            Some(t) => { grid.map.insert(at, t); },
+            None => rest.push((at, triple[2])),
+        }
+    }
+
+    rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Tile {
+        Empty,
+        Wall,
+    }
+
+    impl Glyph for Tile {
+        fn glyph(&self) -> char {
+            match self {
+                Tile::Empty => '.',
+                Tile::Wall => '#',
+            }
+        }
+
+        fn colour(&self) -> Option<Colour> {
+            match self {
+                Tile::Empty => None,
+                Tile::Wall => Some(Colour::Green),
+            }
+        }
+    }
+
+    fn take(r: &mut Renderer<Vec<u8>>) -> String {
+        String::from_utf8(std::mem::take(&mut r.out)).unwrap()
+    }
+
+    #[test]
+    fn test_incremental() {
+        let mut grid = Grid::new_default(Tile::Empty);
+        let mut r = Renderer::new(Vec::new());
+        r.set_colour(false);
+
+        apply_triples(&mut grid, &[0,0,1, 2,1,1], |_, t| Some(if t == 1 { Tile::Wall } else { Tile::Empty }));
+        r.draw(&grid, "one").unwrap();
+        assert_eq!(take(&mut r), format!("{}one{}\n#..\n..#\x1b[4;1H", CLEAR, CLEAR_EOL));
+
+        // nothing changed
+        r.draw(&grid, "one").unwrap();
+        assert_eq!(take(&mut r), "\x1b[4;1H");
+
+        grid.map.insert(Coord::new(1, 0), Tile::Wall);
+        r.draw(&grid, "two").unwrap();
+        assert_eq!(take(&mut r), format!("\x1b[1;1Htwo{}\x1b[2;2H#\x1b[4;1H", CLEAR_EOL));
+
+        // bounds grew, so start again
+        grid.map.insert(Coord::new(0, 2), Tile::Wall);
+        r.draw(&grid, "two").unwrap();
+        assert!(take(&mut r).starts_with(CLEAR));
+
+        assert_eq!(r.frames(), 4);
+    }
+
+    #[test]
+    fn test_colour() {
+        let mut grid = Grid::new();
+        grid.map.insert(Coord::new(0, 0), Tile::Wall);
+        grid.map.insert(Coord::new(1, 0), Tile::Empty);
+
+        let mut r = Renderer::new(Vec::new());
+        r.draw(&grid, "").unwrap();
+        assert!(take(&mut r).contains("\n\x1b[32m#\x1b[0m.\x1b["));
+
+        r.set_colour(false);
+        r.draw(&grid, "").unwrap();
+        assert!(take(&mut r).contains("\n#.\x1b["));
+    }
+
+    #[test]
+    fn test_dump() {
+        let prefix = std::env::temp_dir().join(format!("render-test-{}", std::process::id()));
+        let prefix = prefix.to_str().unwrap();
+
+        let mut grid = Grid::<Word>::new();
+        let mut r = Renderer::new(io::sink());
+        r.set_dump(prefix);
+
+        let rest = apply_triples(&mut grid, &[-1,0,1234, 0,0,2, 1,0,0, 1,1,4], |c, t| {
+            if c.x < 0 { None } else { Some(t) }
+        });
+        assert_eq!(rest, vec![(Coord::new(-1, 0), 1234)]);
+
+        r.draw(&grid, "score 1234").unwrap();
+        grid.map.insert(Coord::new(1, 0), 3);
+        r.draw(&grid, "score 1234").unwrap();
+
+        let frame = |n| {
+            let path = format!("{}-{:06}.txt", prefix, n);
+            let text = fs::read_to_string(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            text
+        };
+        assert_eq!(frame(0), "score 1234\n2 \n 4\n");
+        assert_eq!(frame(1), "score 1234\n23\n 4\n");
+    }
+}
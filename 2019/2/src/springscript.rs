@@ -0,0 +1,469 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::fmt;
+
+// springscript for the day 21 springdroid: up to 15 AND/OR/NOT instructions, then WALK
+// (sensors A-D) or RUN (sensors A-I). T and J start false, and the droid jumps (4 tiles)
+// if J is true at the end
+//
+// sensor readings are a bitmask - bit 0 is A (ground one tile away), bit 8 is I
+
+pub type Sensors = u16;
+
+pub const MAX_ISNS: usize = 15;
+pub const JUMP_LEN: usize = 4;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+    Walk,
+    Run,
+}
+
+impl Mode {
+    pub fn sensors(self) -> usize {
+        match self {
+            Mode::Walk => 4,
+            Mode::Run => 9,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Reg {
+    Sensor(usize), // 0 is A
+    T,
+    J,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Op {
+    And, // y = y & x
+    Or, // y = y | x
+    Not, // y = !x
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Isn {
+    pub op: Op,
+    pub x: Reg,
+    pub y: Reg, // T or J
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Program {
+    pub isns: Vec<Isn>,
+    pub mode: Mode,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "line {}: {}", self.line, self.msg)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+fn parse_reg(s: &str) -> Result<Reg, String> {
+    match s {
+        "T" => Ok(Reg::T),
+        "J" => Ok(Reg::J),
+        _ => match s.as_bytes() {
+            &[c @ b'A'..=b'I'] => Ok(Reg::Sensor((c - b'A') as usize)),
+            _ => Err(format!("unknown register \"{}\"", s)),
+        },
+    }
+}
+
+fn reg_name(reg: Reg) -> char {
+    match reg {
+        Reg::Sensor(n) => (b'A' + n as u8) as char,
+        Reg::T => 'T',
+        Reg::J => 'J',
+    }
+}
+
+pub fn parse(src: &str) -> Result<Program, ScriptError> {
+    let mut isns = Vec::new();
+    let mut mode = None;
+    let mut lineno = 0;
+
+    for (i, line) in src.lines().enumerate() {
+        lineno = i + 1;
+        let err = |msg| ScriptError { line: lineno, msg };
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            continue;
+        }
+
+        if mode.is_some() {
+            return Err(err("instructions after WALK/RUN".into()));
+        }
+
+        let (op, x, y) = match words[..] {
+            ["WALK"] => {
+                mode = Some(Mode::Walk);
+                continue;
+            },
+            ["RUN"] => {
+                mode = Some(Mode::Run);
+                continue;
+            },
+            [op, x, y] => (op, x, y),
+            _ => return Err(err(format!("expected \"OP X Y\", WALK or RUN, found \"{}\"", line.trim()))),
+        };
+
+        let op = match op {
+            "AND" => Op::And,
+            "OR" => Op::Or,
+            "NOT" => Op::Not,
+            _ => return Err(err(format!("unknown instruction \"{}\"", op))),
+        };
+        let x = parse_reg(x).map_err(err)?;
+        let y = match parse_reg(y).map_err(err)? {
+            Reg::Sensor(_) => return Err(err(format!("can't write to sensor {}", y))),
+            y => y,
+        };
+
+        isns.push((lineno, Isn { op, x, y }));
+    }
+
+    let mode = mode.ok_or(ScriptError { line: lineno, msg: "missing WALK or RUN".into() })?;
+
+    if isns.len() > MAX_ISNS {
+        return Err(ScriptError {
+            line: isns[MAX_ISNS].0,
+            msg: format!("too many instructions ({}, max {})", isns.len(), MAX_ISNS),
+        });
+    }
+
+    for &(line, isn) in &isns {
+        if let Reg::Sensor(n) = isn.x {
+            if n >= mode.sensors() {
+                return Err(ScriptError {
+                    line,
+                    msg: format!("sensor {} needs RUN", reg_name(isn.x)),
+                });
+            }
+        }
+    }
+
+    Ok(Program {
+        isns: isns.into_iter().map(|(_, isn)| isn).collect(),
+        mode,
+    })
+}
+
+impl Program {
+    pub fn jumps(&self, sensors: Sensors) -> bool {
+        let (mut t, mut j) = (false, false);
+
+        for isn in &self.isns {
+            let x = match isn.x {
+                Reg::Sensor(n) => sensors & (1 << n) != 0,
+                Reg::T => t,
+                Reg::J => j,
+            };
+            let y = if isn.y == Reg::T { &mut t } else { &mut j };
+
+            match isn.op {
+                Op::And => *y &= x,
+                Op::Or => *y |= x,
+                Op::Not => *y = !x,
+            }
+        }
+
+        j
+    }
+
+    // walks the droid across `hull` ('#' ground, '.' hole) from the first tile. anything past
+    // the end is ground. Err is where it fell
+    pub fn simulate(&self, hull: &str) -> Result<(), usize> {
+        let hull = hull.as_bytes();
+        let ground = |pos: usize| hull.get(pos) != Some(&b'.');
+
+        let mut pos = 0;
+        while pos < hull.len() {
+            if !ground(pos) {
+                return Err(pos);
+            }
+
+            let sensors = (0..self.mode.sensors())
+                .filter(|&n| ground(pos + 1 + n))
+                .fold(0, |acc, n| acc | 1 << n);
+
+            pos += if self.jumps(sensors) { JUMP_LEN } else { 1 };
+        }
+
+        Ok(())
+    }
+}
+
+// ready to send to the droid
+impl fmt::Display for Program {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for isn in &self.isns {
+            let op = match isn.op {
+                Op::And => "AND",
+                Op::Or => "OR",
+                Op::Not => "NOT",
+            };
+            writeln!(fmt, "{} {} {}", op, reg_name(isn.x), reg_name(isn.y))?;
+        }
+
+        match self.mode {
+            Mode::Walk => writeln!(fmt, "WALK"),
+            Mode::Run => writeln!(fmt, "RUN"),
+        }
+    }
+}
+
+// a product term: sensors in `mask` must read as in `value`
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+struct Term {
+    value: Sensors,
+    mask: Sensors,
+}
+
+impl Term {
+    fn covers(&self, sensors: Sensors) -> bool {
+        sensors & self.mask == self.value
+    }
+}
+
+// Quine-McCluskey: prime implicants of on + don't-care, then a greedy cover of on
+fn minimise(n: usize, on: &[Sensors], dc: &[Sensors]) -> Vec<Term> {
+    let all = (1 << n) - 1;
+    let mut terms = on
+        .iter()
+        .chain(dc)
+        .map(|&s| Term { value: s, mask: all })
+        .collect::<Vec<_>>();
+    let mut primes = Vec::new();
+
+    while !terms.is_empty() {
+        let mut used = vec![false; terms.len()];
+        let mut next = Vec::new();
+        let mut seen = HashSet::new();
+
+        for i in 0..terms.len() {
+            for j in i + 1..terms.len() {
+                let (a, b) = (terms[i], terms[j]);
+                let diff = a.value ^ b.value;
+
+                if a.mask == b.mask && diff.count_ones() == 1 {
+                    used[i] = true;
+                    used[j] = true;
+
+                    let merged = Term { value: a.value & !diff, mask: a.mask & !diff };
+                    if seen.insert(merged) {
+                        next.push(merged);
+                    }
+                }
+            }
+        }
+
+        primes.extend(terms.iter().zip(&used).filter(|(_, &u)| !u).map(|(&t, _)| t));
+        terms = next;
+    }
+
+    let mut uncovered = on.to_vec();
+    let mut cover = Vec::new();
+
+    while !uncovered.is_empty() {
+        let best = *primes
+            .iter()
+            .max_by_key(|p| (
+                uncovered.iter().filter(|&&s| p.covers(s)).count(),
+                n as u32 - p.mask.count_ones(),
+            ))
+            .unwrap();
+
+        uncovered.retain(|&s| !best.covers(s));
+        cover.push(best);
+    }
+
+    cover
+}
+
+// J = OR of the terms, each built up in T (or directly in J for the first)
+fn compile(n: usize, terms: &[Term]) -> Vec<Isn> {
+    let isn = |op, x, y| Isn { op, x, y };
+    let mut isns = Vec::new();
+
+    if terms.iter().any(|t| t.mask == 0) {
+        // always true
+        return vec![isn(Op::Not, Reg::J, Reg::J)];
+    }
+
+    for (i, term) in terms.iter().enumerate() {
+        let y = if i == 0 { Reg::J } else { Reg::T };
+        let mut first = true;
+
+        for s in (0..n).filter(|&s| term.mask & (1 << s) != 0) {
+            let x = Reg::Sensor(s);
+            let positive = term.value & (1 << s) != 0;
+
+            match (first, positive) {
+                // J starts false, T may be left over from the previous term
+                (true, true) if y == Reg::J => isns.push(isn(Op::Or, x, y)),
+                (true, true) => {
+                    isns.push(isn(Op::Not, x, y));
+                    isns.push(isn(Op::Not, y, y));
+                },
+                (true, false) => isns.push(isn(Op::Not, x, y)),
+                (false, true) => isns.push(isn(Op::And, x, y)),
+                (false, false) => {
+                    // y & !x == !(!y | x)
+                    isns.push(isn(Op::Not, y, y));
+                    isns.push(isn(Op::Or, x, y));
+                    isns.push(isn(Op::Not, y, y));
+                },
+            }
+            first = false;
+        }
+
+        if y == Reg::T {
+            isns.push(isn(Op::Or, Reg::T, Reg::J));
+        }
+    }
+
+    isns
+}
+
+// finds a program with `truth(sensors)` as its jump decision, None meaning don't care.
+// tries both the function and its complement, keeping the shorter. None if neither fits
+pub fn synthesise<F>(mode: Mode, truth: F) -> Option<Program>
+    where F: Fn(Sensors) -> Option<bool>
+{
+    let n = mode.sensors();
+    let table = (0..1 << n).map(|s| (s, truth(s))).collect::<Vec<_>>();
+
+    let inputs = |want: bool| {
+        table
+            .iter()
+            .filter(|&&(_, t)| t == Some(want))
+            .map(|&(s, _)| s)
+            .collect::<Vec<_>>()
+    };
+    let (on, off) = (inputs(true), inputs(false));
+    let dc = table
+        .iter()
+        .filter(|&&(_, t)| t.is_none())
+        .map(|&(s, _)| s)
+        .collect::<Vec<_>>();
+
+    let direct = compile(n, &minimise(n, &on, &dc));
+
+    let mut inverted = compile(n, &minimise(n, &off, &dc));
+    inverted.push(Isn { op: Op::Not, x: Reg::J, y: Reg::J });
+
+    let isns = if inverted.len() < direct.len() { inverted } else { direct };
+
+    if isns.len() > MAX_ISNS {
+        return None;
+    }
+
+    Some(Program { isns, mode })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ground(sensors: Sensors, reg: char) -> bool {
+        sensors & (1 << (reg as u8 - b'A')) != 0
+    }
+
+    const PART1: &str = "
+        NOT A J
+        NOT B T
+        OR T J
+        NOT C T
+        OR T J
+        AND D J
+        WALK
+    ";
+
+    #[test]
+    fn test_parse_roundtrip() {
+        let program = parse(PART1).unwrap();
+
+        assert_eq!(program.isns.len(), 6);
+        assert_eq!(program.mode, Mode::Walk);
+        assert_eq!(program.isns[5], Isn { op: Op::And, x: Reg::Sensor(3), y: Reg::J });
+
+        assert_eq!(parse(&program.to_string()), Ok(program));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |line, msg: &str| Err(ScriptError { line, msg: msg.into() });
+
+        assert_eq!(parse("NOT A J"), err(1, "missing WALK or RUN"));
+        assert_eq!(parse("NOT A B\nWALK"), err(1, "can't write to sensor B"));
+        assert_eq!(parse("NOT X J\nWALK"), err(1, "unknown register \"X\""));
+        assert_eq!(parse("XOR A J\nWALK"), err(1, "unknown instruction \"XOR\""));
+        assert_eq!(parse("WALK\nNOT A J"), err(2, "instructions after WALK/RUN"));
+        assert_eq!(parse("NOT A J\nAND E J\nWALK"), err(2, "sensor E needs RUN"));
+        assert!(parse("NOT A J\nAND E J\nRUN").is_ok());
+
+        let long = "NOT A J\n".repeat(16) + "WALK";
+        assert_eq!(parse(&long), err(16, "too many instructions (16, max 15)"));
+    }
+
+    #[test]
+    fn test_simulate() {
+        let program = parse(PART1).unwrap();
+
+        assert_eq!(program.simulate("#####.###########"), Ok(()));
+        assert_eq!(program.simulate("#####...#########"), Ok(()));
+        assert_eq!(program.simulate("#####..#.########"), Ok(()));
+
+        // jumps from 4 as soon as it sees the hole at 7, landing at 8 with the hole at 9 right
+        // in front (waiting until 6 would have cleared everything)
+        assert_eq!(program.simulate("#######.#.##.####"), Err(9));
+        // never jumps
+        assert_eq!(parse("WALK").unwrap().simulate("#####.###"), Err(5));
+    }
+
+    #[test]
+    fn test_synthesise() {
+        // part 1: a gap in A-C, with D to land on
+        let truth = |s| Some(!(ground(s, 'A') && ground(s, 'B') && ground(s, 'C')) && ground(s, 'D'));
+        let program = synthesise(Mode::Walk, truth).unwrap();
+
+        assert!(program.isns.len() <= MAX_ISNS);
+        for s in 0..16 {
+            assert_eq!(Some(program.jumps(s)), truth(s), "sensors {:04b}", s);
+        }
+        assert_eq!(program.simulate("#####..#.########"), Ok(()));
+
+        // part 2, with RUN
+        let truth = |s| Some(
+            (!ground(s, 'A') || !ground(s, 'B') || (!ground(s, 'C') && ground(s, 'H')))
+            && ground(s, 'D'));
+        let program = synthesise(Mode::Run, truth).unwrap();
+
+        assert!(program.isns.len() <= MAX_ISNS);
+        for s in 0..1 << 9 {
+            assert_eq!(Some(program.jumps(s)), truth(s), "sensors {:09b}", s);
+        }
+    }
+
+    #[test]
+    fn test_synthesise_dont_care() {
+        // only care when D is ground, so "jump iff A is a hole" should be all that's needed
+        let truth = |s| if ground(s, 'D') { Some(!ground(s, 'A')) } else { None };
+        let program = synthesise(Mode::Walk, truth).unwrap();
+
+        assert_eq!(program.isns, vec![Isn { op: Op::Not, x: Reg::Sensor(0), y: Reg::J }]);
+    }
+}
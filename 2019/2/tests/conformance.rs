@@ -0,0 +1,418 @@
+// random well-formed programs, run a step at a time by `IntCodeMachine` and by a deliberately
+// simple reference evaluator, comparing state after every step. `FastMachine` is stepped
+// alongside and held to the same
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[path = "../src/lib.rs"]
+mod lib;
+use lib::{IntCodeMachine, IntcodeError, State, Word, DEFAULT_ADDRESS_LIMIT, DENSE_MAX, OPCODES};
+
+#[path = "../src/fast.rs"]
+mod fast;
+use fast::FastMachine;
+
+const CASES: u64 = 2000;
+const MAX_STEPS: usize = 200;
+const DATA: usize = 16; // scratch words after the code
+
+// xorshift64*, so failures can be reproduced from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn range(&mut self, lo: Word, hi: Word) -> Word {
+        lo + self.below((hi - lo + 1) as usize) as Word
+    }
+}
+
+struct Reference {
+    mem: HashMap<usize, Word>,
+    ip: usize,
+    rb: Word,
+    state: State,
+    dense: usize, // how far the machines' dense memory should have grown
+    seen: HashSet<(Word, Word)>, // (opcode, mode) of every operand used
+}
+
+impl Reference {
+    fn new(program: &[Word]) -> Self {
+        Self {
+            mem: program.iter().cloned().enumerate().collect(),
+            ip: 0,
+            rb: 0,
+            state: State::Running,
+            dense: program.len(),
+            seen: HashSet::new(),
+        }
+    }
+
+    fn read(&self, address: usize) -> Result<Word, IntcodeError> {
+        if address >= DEFAULT_ADDRESS_LIMIT {
+            return Err(IntcodeError::OutOfRange { ip: self.ip, address, limit: DEFAULT_ADDRESS_LIMIT });
+        }
+        Ok(self.mem.get(&address).cloned().unwrap_or(0))
+    }
+
+    fn write(&mut self, address: usize, w: Word) -> Result<(), IntcodeError> {
+        self.read(address)?;
+        self.mem.insert(address, w);
+        if address < DENSE_MAX {
+            self.dense = self.dense.max(address + 1);
+        }
+        Ok(())
+    }
+
+    // an operand's address, or its value if it's immediate
+    fn operand(&mut self, isn: Word, i: usize) -> Result<Result<usize, Word>, IntcodeError> {
+        let raw = self.read(self.ip + 1 + i)?;
+        let mode = isn / [100, 1000, 10000][i] % 10;
+        self.seen.insert((isn % 100, mode));
+
+        let address = match mode {
+            0 => raw,
+            1 => return Ok(Err(raw)),
+            2 => self.rb.checked_add(raw).ok_or(IntcodeError::Overflow { ip: self.ip, isn })?,
+            _ => return Err(IntcodeError::InvalidMode { ip: self.ip, isn, operand: i, mode }),
+        };
+
+        if address < 0 {
+            return Err(IntcodeError::NegativeAddress { ip: self.ip, isn, operand: i, address });
+        }
+        Ok(Ok(address as usize))
+    }
+
+    fn get(&mut self, isn: Word, i: usize) -> Result<Word, IntcodeError> {
+        match self.operand(isn, i)? {
+            Ok(address) => self.read(address),
+            Err(value) => Ok(value),
+        }
+    }
+
+    fn dest(&mut self, isn: Word, i: usize) -> Result<usize, IntcodeError> {
+        match self.operand(isn, i)? {
+            Ok(address) => Ok(address),
+            Err(_) => Err(IntcodeError::ImmediateWrite { ip: self.ip, isn, operand: i }),
+        }
+    }
+
+    fn jump(&mut self, isn: Word, take: bool, target: Word) -> Result<(), IntcodeError> {
+        if !take {
+            self.ip += 3;
+        } else if target < 0 {
+            return Err(IntcodeError::NegativeAddress { ip: self.ip, isn, operand: 1, address: target });
+        } else {
+            self.ip = target as usize;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, input: &mut VecDeque<Word>, output: &mut Vec<Word>) -> Result<(), IntcodeError> {
+        let isn = self.read(self.ip)?;
+        self.state = State::Running;
+
+        match isn % 100 {
+            op @ 1 | op @ 2 | op @ 7 | op @ 8 => {
+                let (a, b) = (self.get(isn, 0)?, self.get(isn, 1)?);
+                let dest = self.dest(isn, 2)?;
+
+                let result = match op {
+                    1 => a.checked_add(b).ok_or(IntcodeError::Overflow { ip: self.ip, isn })?,
+                    2 => a.checked_mul(b).ok_or(IntcodeError::Overflow { ip: self.ip, isn })?,
+                    7 => (a < b) as Word,
+                    _ => (a == b) as Word,
+                };
+
+                self.write(dest, result)?;
+                self.ip += 4;
+            },
+            3 => {
+                let dest = self.dest(isn, 0)?;
+                match input.pop_front() {
+                    Some(w) => {
+                        self.write(dest, w)?;
+                        self.ip += 2;
+                    },
+                    None => self.state = State::AwaitingInput,
+                }
+            },
+            4 => {
+                output.push(self.get(isn, 0)?);
+                self.ip += 2;
+            },
+            5 => {
+                let (test, target) = (self.get(isn, 0)?, self.get(isn, 1)?);
+                self.jump(isn, test != 0, target)?;
+            },
+            6 => {
+                let (test, target) = (self.get(isn, 0)?, self.get(isn, 1)?);
+                self.jump(isn, test == 0, target)?;
+            },
+            9 => {
+                let by = self.get(isn, 0)?;
+                self.rb = self.rb.checked_add(by).ok_or(IntcodeError::Overflow { ip: self.ip, isn })?;
+                self.ip += 2;
+            },
+            99 => {
+                self.seen.insert((99, 0));
+                self.state = State::Halted;
+            },
+            _ => return Err(IntcodeError::UnknownOpcode { ip: self.ip, isn }),
+        }
+
+        Ok(())
+    }
+}
+
+// a handful of random instructions, a halt, then some data. operands mostly refer to the
+// program itself, so there's plenty of self-modification
+fn generate(rng: &mut Rng) -> Vec<Word> {
+    let ops = (0..rng.range(1, 12))
+        .map(|_| &OPCODES[rng.below(OPCODES.len())])
+        .collect::<Vec<_>>();
+
+    let code_len = ops.iter().map(|o| o.nparams + 1).sum::<usize>() + 1;
+    let len = code_len + DATA;
+    let starts = ops
+        .iter()
+        .scan(0, |at, o| {
+            let start = *at;
+            *at += o.nparams + 1;
+            Some(start as Word)
+        })
+        .collect::<Vec<_>>();
+
+    let mut program = Vec::new();
+    for op in ops {
+        let modes = (0..op.nparams)
+            .map(|i| if op.writes && i == op.nparams - 1 { [0, 2][rng.below(2)] } else { rng.range(0, 2) })
+            .collect::<Vec<_>>();
+
+        program.push(op.op + modes.iter().rev().fold(0, |acc, m| acc * 10 + m) * 100);
+
+        for (i, &mode) in modes.iter().enumerate() {
+            let is_target = (op.op == lib::OP_JNZ || op.op == lib::OP_JZ) && i == 1;
+
+            program.push(match mode {
+                1 if is_target => starts[rng.below(starts.len())],
+                1 => rng.range(-10, 10),
+                2 => rng.range(-4, len as Word - 1),
+                _ => rng.below(len) as Word,
+            });
+        }
+    }
+
+    program.push(lib::OP_HALT);
+    program.extend((0..DATA).map(|_| rng.range(-3, 20)));
+    program
+}
+
+fn compare_memory(reference: &Reference, peek: impl Fn(usize) -> Word, dense: &[Word]) -> Result<(), String> {
+    for (&address, &w) in &reference.mem {
+        if peek(address) != w {
+            return Err(format!("[{}] = {}, expected {}", address, peek(address), w));
+        }
+    }
+    if dense.len() != reference.dense {
+        return Err(format!("dense memory is {} long, expected {}", dense.len(), reference.dense));
+    }
+    for (address, &w) in dense.iter().enumerate() {
+        if w != 0 && !reference.mem.contains_key(&address) {
+            return Err(format!("[{}] = {}, expected 0", address, w));
+        }
+    }
+    Ok(())
+}
+
+// returns the reference, so callers can check coverage
+fn check(program: &[Word], inputs: &[Word]) -> Result<Reference, String> {
+    let mut machine = IntCodeMachine::new(program, false);
+    let mut fast = FastMachine::new(program);
+    let mut reference = Reference::new(program);
+
+    let mut m_in = VecDeque::from(inputs.to_vec());
+    let mut f_in = m_in.clone();
+    let mut r_in = m_in.clone();
+    let (mut m_out, mut f_out, mut r_out) = (Vec::new(), Vec::new(), Vec::new());
+
+    for step in 0..MAX_STEPS {
+        let expected = reference.step(&mut r_in, &mut r_out);
+        let got = machine.step(&mut m_in, &mut m_out);
+        let got_fast = fast.step(&mut f_in, &mut f_out);
+
+        let theirs = (&expected, reference.ip, reference.rb, reference.state, &r_out);
+        let ours = (&got, machine.ip(), machine.relative_base(), machine.state(), &m_out);
+        if ours != theirs {
+            return Err(format!("step {}: got {:?}, expected {:?}", step, ours, theirs));
+        }
+        let ours = (&got_fast, fast.ip(), fast.relative_base(), fast.state(), &f_out);
+        if ours != theirs {
+            return Err(format!("fast step {}: got {:?}, expected {:?}", step, ours, theirs));
+        }
+
+        if expected.is_err() || reference.state != State::Running {
+            break;
+        }
+    }
+
+    compare_memory(&reference, |a| machine.peek(a), machine.memory())?;
+    compare_memory(&reference, |a| fast.peek(a), fast.memory()).map_err(|e| format!("fast: {}", e))?;
+
+    Ok(reference)
+}
+
+#[test]
+fn random_programs() {
+    let mut seen = HashSet::new();
+
+    for seed in 1..=CASES {
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let program = generate(&mut rng);
+        let inputs = (0..rng.below(4)).map(|_| rng.range(-5, 30)).collect::<Vec<_>>();
+
+        match check(&program, &inputs) {
+            Ok(reference) => seen.extend(reference.seen),
+            Err(e) => panic!("seed {}: {}\nprogram: {:?}\ninputs: {:?}", seed, e, program, inputs),
+        }
+    }
+
+    // every opcode was run with every mode it allows
+    for op in OPCODES.iter() {
+        for i in 0..op.nparams {
+            let modes: &[Word] = if op.writes && i == op.nparams - 1 { &[0, 2] } else { &[0, 1, 2] };
+            for &mode in modes {
+                assert!(seen.contains(&(op.op, mode)), "never ran {} with mode {}", op.mnemonic, mode);
+            }
+        }
+    }
+    assert!(seen.contains(&(lib::OP_HALT, 0)));
+}
+
+#[test]
+fn relative_base_writes() {
+    let program = [
+        109,20,         //  0: arb #20
+        203,0,          //  2: in [rb+0]
+        21101,3,4,1,    //  4: add #3, #4, [rb+1]
+        109,-19,        //  8: arb #-19
+        22201,19,20,21, // 10: add [rb+19], [rb+20], [rb+21]
+        204,21,         // 14: out [rb+21]
+        99,             // 16: hlt
+        0,0,0,0,0,0,    // 17
+    ];
+
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert_eq!(machine.interpret_async(&mut vec![5]), vec![12]);
+    assert_eq!(&machine.memory()[20..23], &[5, 7, 12]);
+    assert_eq!(machine.relative_base(), 1);
+
+    check(&program, &[5]).unwrap();
+
+    // relative writes below zero fault
+    let program = [109,-3, 21101,1,1,2, 99];
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert_eq!(
+        machine.try_interpret_async(&mut vec![], &mut vec![]),
+        Err(IntcodeError::NegativeAddress { ip: 2, isn: 21101, operand: 2, address: -1 }));
+
+    check(&program, &[]).unwrap();
+}
+
+#[test]
+fn self_modifying() {
+    let program = [
+        1101,98,1,10,   //  0: add #98, #1, [10]  - the halt at the end
+        1101,1,3,9,     //  4: add #1, #3, [9]    - out's operand
+        1104,0,         //  8: out #0
+        0,              // 10
+    ];
+
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert_eq!(machine.interpret_async(&mut vec![]), vec![4]);
+    assert_eq!(machine.state(), State::Halted);
+
+    check(&program, &[]).unwrap();
+
+    // a loop which counts down by rewriting its own immediate operand
+    let program = [
+        1001,7,-1,7,    //  0: add [7], #-1, [7]
+        4,7,            //  4: out [7]
+        1105,3,0,       //  6: jnz #3, #0  - the 3 is [7]
+        99,
+    ];
+
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert_eq!(machine.interpret_async(&mut vec![]), vec![2, 1, 0]);
+
+    check(&program, &[]).unwrap();
+}
+
+#[test]
+fn large_addresses() {
+    let far = 1_000_000;
+    let program = [
+        1101,5,6,far,               //  0: add #5, #6, [far]
+        4,far,                      //  4: out [far]
+        109,2_000_000_000,          //  6: arb #2e9
+        21101,1,2,0,                //  8: add #1, #2, [rb]
+        204,0,                      // 12: out [rb]
+        4,far + 1,                  // 14: out [far + 1] - never written
+        99,
+    ];
+
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert_eq!(machine.interpret_async(&mut vec![]), vec![11, 3, 0]);
+
+    // the far writes are paged rather than growing memory
+    assert!(machine.memory().len() < 1 << 17);
+    assert_eq!(machine.snapshot().sparse, vec![(far as usize, 11), (2_000_000_000, 3)]);
+
+    check(&program, &[]).unwrap();
+
+    let mut machine = IntCodeMachine::new(&program, false);
+    machine.set_address_limit(1 << 19);
+    assert_eq!(
+        machine.try_interpret_async(&mut vec![], &mut vec![]),
+        Err(IntcodeError::OutOfRange { ip: 0, address: far as usize, limit: 1 << 19 }));
+
+    // and at the default limit
+    let program = [1101,1,1,DEFAULT_ADDRESS_LIMIT as Word, 99];
+    let mut machine = IntCodeMachine::new(&program, false);
+    assert!(machine.try_interpret_async(&mut vec![], &mut vec![]).is_err());
+
+    check(&program, &[]).unwrap();
+}
+
+#[test]
+fn overflow() {
+    let cases: &[(&[Word], IntcodeError)] = &[
+        (&[1101,Word::MAX,1,0,99], IntcodeError::Overflow { ip: 0, isn: 1101 }),
+        (&[1102,Word::MIN,-1,0,99], IntcodeError::Overflow { ip: 0, isn: 1102 }),
+        (&[109,Word::MAX,109,1,99], IntcodeError::Overflow { ip: 2, isn: 109 }),
+        (&[109,Word::MAX,204,1,99], IntcodeError::Overflow { ip: 2, isn: 204 }),
+    ];
+
+    for (program, error) in cases {
+        let mut reference = Reference::new(program);
+        let mut output = Vec::new();
+        let stopped = (0..MAX_STEPS)
+            .map(|_| reference.step(&mut VecDeque::new(), &mut output))
+            .find(Result::is_err);
+        assert_eq!(stopped, Some(Err(error.clone())));
+
+        let mut machine = IntCodeMachine::new(program, false);
+        assert_eq!(machine.try_interpret_async(&mut vec![], &mut vec![]), Err(error.clone()));
+
+        check(program, &[]).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
 use std::fs;
 
+use numtheory::{mod_inverse, mod_mul};
+
 /*macro_rules! deck {
     deck(n) {
     }
@@ -32,6 +34,76 @@ impl Shuffle {
     {
         Self (iter.into_iter().collect())
     }
+
+    fn affine(&self, n: u64) -> Affine {
+        self.0
+            .iter()
+            .fold(Affine::identity(n), |acc, ent| acc.then(&Affine::from_ent(ent, n)))
+    }
+}
+
+// where a shuffle moves the card at position x to, as a*x + b (mod n). n must be small enough
+// that the sum of two residues fits in an i64
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Affine {
+    a: i64,
+    b: i64,
+    n: i64,
+}
+
+impl Affine {
+    fn identity(n: u64) -> Self {
+        assert!(n > 0 && n < 1 << 62);
+        Self { a: 1, b: 0, n: n as _ }
+    }
+
+    fn new(a: i64, b: i64, n: i64) -> Self {
+        Self { a: a.rem_euclid(n), b: b.rem_euclid(n), n }
+    }
+
+    fn from_ent(ent: &ShuffleEnt, n: u64) -> Self {
+        let n = Self::identity(n).n;
+
+        match *ent {
+            ShuffleEnt::Deal => Self::new(-1, -1, n),
+            ShuffleEnt::Cut(c) => Self::new(1, -(c as i64), n),
+            ShuffleEnt::Increment(i) => Self::new(i as _, 0, n),
+        }
+    }
+
+    fn apply(&self, x: i64) -> i64 {
+        (mod_mul(self.a, x, self.n) + self.b) % self.n
+    }
+
+    // `self`, followed by `next`
+    fn then(&self, next: &Self) -> Self {
+        assert_eq!(self.n, next.n);
+
+        Self::new(mod_mul(next.a, self.a, self.n), next.apply(self.b), self.n)
+    }
+
+    // None if a isn't coprime with n, i.e. the shuffle loses cards
+    fn inverse(&self) -> Option<Self> {
+        let inv = mod_inverse(self.a, self.n)?;
+
+        Some(Self::new(inv, mod_mul(-self.b, inv, self.n), self.n))
+    }
+
+    // `self` repeated `times` times, by squaring
+    fn pow(&self, mut times: u64) -> Self {
+        let mut result = Self::identity(self.n as _);
+        let mut square = *self;
+
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(&square);
+            }
+            square = square.then(&square);
+            times >>= 1;
+        }
+
+        result
+    }
 }
 
 impl<T> CopyRight for Vec<T>
@@ -181,7 +253,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("card 2019: {:?}", deck.cards[2019]);
     */
 
-    println!("{:?}", deck.cards.iter().position(|&Card(x)| x == 2019));
+    let pos = deck.cards.iter().position(|&Card(x)| x == 2019);
+    println!("{:?}", pos);
+    assert_eq!(pos, Some(shuffle.affine(N as _).apply(req) as usize));
+
+    const BIG: u64 = 119_315_717_514_047;
+    const TIMES: u64 = 101_741_582_076_661;
+
+    let unshuffle = shuffle
+        .affine(BIG)
+        .pow(TIMES)
+        .inverse()
+        .ok_or("shuffle isn't invertible")?;
+
+    println!("card at 2020: {}", unshuffle.apply(2020));
 
     Ok(())
 }
@@ -269,6 +354,22 @@ mod tests {
         deck.apply(&shuffle);
 
         assert_eq!(deck, expected);
+        assert_affine(&shuffle, &deck);
+    }
+
+    // the affine map sends each card to where the physical shuffle put it, and its inverse
+    // tells us which card is at each position
+    fn assert_affine(shuffle: &Shuffle, deck: &Deck) {
+        let n = deck.cards.len();
+        let affine = shuffle.affine(n as _);
+        let inverse = affine.inverse().unwrap();
+
+        for (pos, &Card(card)) in deck.cards.iter().enumerate() {
+            assert_eq!(affine.apply(card as _), pos as i64);
+            assert_eq!(inverse.apply(pos as _), card as i64);
+        }
+
+        assert_eq!(affine.then(&inverse), Affine::identity(n as _));
     }
 
     #[test]
@@ -321,4 +422,64 @@ mod tests {
             ",
             &vec![9, 2, 5, 8, 1, 4, 7, 0, 3, 6]);
     }
+
+    const EG4: &str = "
+        deal into new stack
+        cut -2
+        deal with increment 7
+        cut 8
+        cut -4
+        deal with increment 7
+        cut 3
+        deal with increment 9
+        deal with increment 3
+        cut -1
+    ";
+
+    #[test]
+    fn affine_entries() {
+        for ent in &["deal into new stack", "cut 3", "cut -4", "deal with increment 3"] {
+            let shuffle = parse(ent);
+            let mut deck = Deck::new(10);
+            deck.apply(&shuffle);
+
+            assert_affine(&shuffle, &deck);
+        }
+    }
+
+    #[test]
+    fn affine_pow() {
+        let shuffle = parse(EG4);
+        let n = 11;
+
+        let mut deck = Deck::new(n);
+        for times in 0..25 {
+            let affine = shuffle.affine(n as _).pow(times);
+
+            for (pos, &Card(card)) in deck.cards.iter().enumerate() {
+                assert_eq!(affine.apply(card as _), pos as i64, "{} times", times);
+            }
+
+            deck.apply(&shuffle);
+        }
+    }
+
+    #[test]
+    fn affine_huge() {
+        let n = 119_315_717_514_047;
+        let affine = parse(EG4).affine(n);
+        let times = 101_741_582_076_661;
+
+        let there = affine.pow(times);
+        let back = there.inverse().unwrap();
+
+        for &card in &[0, 2020, n as i64 - 1] {
+            assert_eq!(back.apply(there.apply(card)), card);
+        }
+
+        // squaring agrees with composing
+        assert_eq!(affine.pow(6), affine.pow(2).then(&affine.pow(4)));
+
+        assert_eq!(Affine::new(2, 0, 10).inverse(), None);
+    }
 }
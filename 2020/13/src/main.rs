@@ -10,7 +10,7 @@ fn main() -> Result<()> {
     println!("Part 1: {}", part1(earliest, &times));
 
     let times = parse2(&s)?;
-    println!("Part 2: {}", part2(&times) + 1);
+    println!("Part 2: {}", part2(&times));
 
     Ok(())
 }
@@ -34,7 +34,7 @@ fn parse(s: &str) -> Result<(usize, Vec<usize>)> {
     Ok((earliest, times))
 }
 
-fn part1(earliest: usize, times: &Vec<usize>) -> usize {
+fn part1(earliest: usize, times: &[usize]) -> usize {
     let (bus_id, wait) = times.iter()
         .map(|&time| {
             let mut t = time;
@@ -76,96 +76,23 @@ fn parse2(s: &str) -> Result<Vec<Option<usize>>> {
     Ok(parts)
 }
 
-fn to_f64(u: usize) -> f64 { u as _ }
-
-fn part2(times: &Vec<Option<usize>>) -> usize {
-    struct R {
-        // find some 'x' such that for all entries,
-        // we get a remainder of 'a' when divided by 'n'
-        a: usize,
-        n: usize,
-    }
-
-    let start_modulo = times
-        .iter()
-        .enumerate()
-        .map(|(i, x)| x.map(|_| i))
-        .filter_map(|x| x)
-        .last()
-        .unwrap();
-
-    let times = times
+fn part2(times: &[Option<usize>]) -> usize {
+    // find some 't' such that bus 'n', at offset 'i', leaves at t + i,
+    // i.e. t leaves a remainder of -i when divided by n
+    let congruences = times
         .iter()
         .enumerate()
-        .map(|(i, time)| time.map(|time| R { a: i, n: time }))
-        .filter_map(|x| x)
+        .filter_map(|(i, time)| time.map(|n| (-(i as i64), n as i64)))
         .collect::<Vec<_>>();
 
-    let modulos_adjusted: Vec<_> = times.iter().map(|&R { a, n: _ }| {
-        start_modulo - a
-    }).map(to_f64).collect();
-
     #[cfg(feature = "show-buses")]
-    times.iter().enumerate().for_each(|(i, R { a, n })| {
-        println!("bus {} leaves every {} mins, using remainder {}", a, n, modulos_adjusted[i]);
+    congruences.iter().for_each(|(a, n)| {
+        println!("bus {} leaves every {} mins, using remainder {}", -a, n, a.rem_euclid(*n));
     });
 
-    let x = chinese_remainder(
-        modulos_adjusted,
-        times.iter().map(|&R { a: _, n }| n).map(to_f64).collect(),
-    );
-
-    x as usize - start_modulo
-
-    // problems:
-    // 1)
-    //   need to reverse the modulo we want, since we're looking for
-    //     1068781    D       .       .       .       .
-    //     1068782    .       D       .       .       .
-    //     1068783    .       .       .       .       .
-    //     1068784    .       .       .       .       .
-    //     1068785    .       .       D       .       .
-    //     1068786    .       .       .       .       .
-    //     1068787    .       .       .       D       .
-    //     1068788    D       .       .       .       D
-    //   but chinese-remainder gives us:
-    //     1068781            .       .       .       D
-    //     1068782    .       .       .       D       .
-    //     1068783    .       .       .       .       .
-    //     1068784    .       .       D       .       .
-    //     1068785    .       .       .       .       .
-    //     1068786    .       .       .       .       .
-    //     1068787    .       D       .       .       .
-    //     1068788    D       .       .       .       .
-    //
-    // we then adjust this by the modulo adjustment to get flip back to our answer
-}
-
-fn chinese_remainder(a: Vec<f64>, n: Vec<f64>) -> f64 {
-    let common_product: f64 = n.iter().cloned().product();
-
-    let n_divided = n.iter().cloned().map(|ni| common_product / ni).collect::<Vec<f64>>();
-    let mut cr = 0.0;
-
-    for i in 0..n_divided.len() {
-        cr = (
-            cr + (
-                (a[i] * n_divided[i]) % common_product
-                * modular_multiplicative_inverse_brute(n_divided[i] as _, n[i] as _) as f64)
-            % common_product
-        ) % common_product;
-    }
-
-    cr
-}
+    let (t, _) = numtheory::crt(&congruences).expect("buses never line up");
 
-fn modular_multiplicative_inverse_brute(e: usize, modu: usize) -> usize {
-    for i in 1..modu {
-        if (e * i) % modu == 1 {
-            return i;
-        }
-    }
-    panic!();
+    t as usize
 }
 
 #[test]
@@ -1,3 +1,7 @@
+use numtheory::{discrete_log, mod_pow};
+
+const MODULUS: i64 = 20201227;
+
 fn main() {
     let ns = [15733400, 6408062];
     let subject = 7;
@@ -6,37 +10,12 @@ fn main() {
 }
 
 fn part1([a, b]: [usize; 2], subject: usize) -> usize {
-    let mut value = subject;
-
-    for i in 2.. {
-        value = transform(value, subject);
-
-        if value == a {
-            // a loop size is `i`
-
-            let subject = b;
-            let mut sk = subject;
-            for _ in 0..=i-2 {
-                sk = transform(sk, subject);
-            }
-            return sk;
-        }
-        if value == b {
-            // b loop size is `i`
-
-            let subject = a;
-            let mut sk = subject;
-            for _ in 0..=i-2 {
-                sk = transform(sk, subject);
-            }
-            return sk;
-        }
-    }
-    unreachable!();
-}
+    // transforming is subject^loop_size, so a's loop size is a discrete log,
+    // and the encryption key is b transformed by it
+    let loop_size = discrete_log(subject as i64, a as i64, MODULUS)
+        .expect("no loop size for public key");
 
-fn transform(value: usize, subject: usize) -> usize {
-    (value * subject) % 20201227
+    mod_pow(b as i64, loop_size, MODULUS) as usize
 }
 
 #[test]
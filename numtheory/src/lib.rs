@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+// integer-exact number theory, shared between the days which need it. everything works on
+// i64, going via i128 wherever a product could overflow. moduli must be positive and
+// results are always reduced into 0..m
+
+// unsigned, as gcd(i64::MIN, 0) = 2^63 doesn't fit in an i64
+pub fn gcd(a: i64, b: i64) -> u64 {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+
+    a
+}
+
+fn gcd_i128(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+
+    a
+}
+
+// None if the lcm doesn't fit in an i64
+pub fn lcm(a: i64, b: i64) -> Option<i64> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+
+    let (a, b) = (a as i128, b as i128);
+    i64::try_from((a / gcd_i128(a, b) * b).abs()).ok()
+}
+
+pub fn lcm_all(ents: &[i64]) -> Option<i64> {
+    ents.iter().try_fold(1, |acc, &e| lcm(acc, e))
+}
+
+// (g, x, y) such that a*x + b*y = g = gcd(a, b). None only if g doesn't fit in an i64, when
+// a and b are both 0 or i64::MIN
+pub fn ext_gcd(a: i64, b: i64) -> Option<(i64, i64, i64)> {
+    fn ext_gcd_i128(a: i128, b: i128) -> (i128, i128, i128) {
+        if b == 0 {
+            return (a.abs(), a.signum(), 0);
+        }
+
+        let (g, x, y) = ext_gcd_i128(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+
+    let (g, x, y) = ext_gcd_i128(a as i128, b as i128);
+    Some((i64::try_from(g).ok()?, x as i64, y as i64))
+}
+
+pub fn mod_mul(a: i64, b: i64, m: i64) -> i64 {
+    (a as i128 * b as i128).rem_euclid(m as i128) as i64
+}
+
+pub fn mod_pow(base: i64, mut exp: u64, m: i64) -> i64 {
+    let mut base = base.rem_euclid(m);
+    let mut result = 1 % m;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mod_mul(result, base, m);
+        }
+        base = mod_mul(base, base, m);
+        exp >>= 1;
+    }
+
+    result
+}
+
+// x such that a*x = 1 (mod m), if a and m are coprime
+pub fn mod_inverse(a: i64, m: i64) -> Option<i64> {
+    let (g, x, _) = ext_gcd(a.rem_euclid(m), m)?;
+
+    if g == 1 {
+        Some(x.rem_euclid(m))
+    } else {
+        None
+    }
+}
+
+// solves x = a (mod n) for each (a, n), returning x and the modulus it's unique under (the
+// lcm of all n). the moduli needn't be coprime, but then the remainders have to agree. None
+// too if that lcm doesn't fit in an i64
+pub fn crt(congruences: &[(i64, i64)]) -> Option<(i64, i64)> {
+    let mut x: i128 = 0;
+    let mut l: i128 = 1;
+
+    for &(a, n) in congruences {
+        let (a, n) = (a as i128, n as i128);
+        let g = gcd_i128(l, n);
+
+        let diff = a - x;
+        if diff % g != 0 {
+            return None;
+        }
+
+        // x + l*k = a (mod n), so (l/g)*k = diff/g (mod n/g)
+        let step = n / g;
+        let inv = mod_inverse((l / g % step) as i64, step as i64)? as i128;
+        let k = (diff / g).rem_euclid(step) * inv % step;
+
+        x += l * k;
+        l *= step;
+        x = x.rem_euclid(l);
+
+        if l > i64::MAX as i128 {
+            return None;
+        }
+    }
+
+    Some((x as i64, l as i64))
+}
+
+// smallest e such that base^e = target (mod m), via baby-step giant-step. base has to be
+// coprime to m - fine for the prime moduli we deal with
+pub fn discrete_log(base: i64, target: i64, m: i64) -> Option<u64> {
+    let target = target.rem_euclid(m);
+    let steps = (m as f64).sqrt().ceil() as i64;
+
+    // baby steps: base^j for j < steps, keeping the smallest j for each
+    let mut table = HashMap::new();
+    let mut cur = 1 % m;
+    for j in 0..steps {
+        table.entry(cur).or_insert(j);
+        cur = mod_mul(cur, base, m);
+    }
+
+    // giant steps: target * base^(-steps*i)
+    let factor = mod_pow(mod_inverse(base, m)?, steps as u64, m);
+    let mut gamma = target;
+    for i in 0..steps {
+        if let Some(&j) = table.get(&gamma) {
+            return Some((i * steps + j) as u64);
+        }
+        gamma = mod_mul(gamma, factor, m);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd_lcm() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-12, 18), 6);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(i64::MIN, 0), 1 << 63);
+        assert_eq!(gcd(i64::MIN, i64::MIN), 1 << 63);
+        assert_eq!(gcd(i64::MIN, 6), 2);
+        assert_eq!(gcd(i64::MIN, i64::MAX), 1);
+        assert_eq!(lcm(4, 6), Some(12));
+        assert_eq!(lcm(-4, 6), Some(12));
+        assert_eq!(lcm(0, 6), Some(0));
+        assert_eq!(lcm_all(&[]), Some(1));
+        assert_eq!(lcm_all(&[18, 28, 44]), Some(2772));
+        assert_eq!(lcm_all(&[2028, 5898, 4702]), Some(4686774924));
+
+        // overflowing
+        assert_eq!(lcm(i64::MAX, i64::MAX - 1), None);
+        assert_eq!(lcm(i64::MIN, 1), None);
+        assert_eq!(lcm_all(&[1 << 31, (1 << 31) - 1, (1 << 31) + 1]), None);
+    }
+
+    #[test]
+    fn test_ext_gcd() {
+        let cases = [
+            (240, 46), (46, 240), (-7, 3), (17, 0), (1_000_000_007, 998_244_353),
+            (i64::MIN, 6), (i64::MIN, i64::MAX), (i64::MAX, i64::MIN), (i64::MIN, -1),
+        ];
+        for &(a, b) in &cases {
+            let (g, x, y) = ext_gcd(a, b).unwrap();
+            assert_eq!(g as u64, gcd(a, b));
+            assert_eq!(a as i128 * x as i128 + b as i128 * y as i128, g as i128);
+        }
+
+        // gcd 2^63
+        assert_eq!(ext_gcd(i64::MIN, 0), None);
+        assert_eq!(ext_gcd(0, i64::MIN), None);
+        assert_eq!(ext_gcd(i64::MIN, i64::MIN), None);
+    }
+
+    #[test]
+    fn test_mod_inverse_pow() {
+        assert_eq!(mod_inverse(3, 11), Some(4));
+        assert_eq!(mod_inverse(-3, 11), Some(7));
+        assert_eq!(mod_inverse(6, 9), None);
+
+        assert_eq!(mod_pow(2, 10, 1000), 24);
+        assert_eq!(mod_pow(-2, 3, 7), 6);
+        assert_eq!(mod_pow(5, 0, 1), 0);
+
+        // fermat, with a modulus whose squares overflow i64
+        let p = 4_611_686_018_427_387_847; // 2^62 - 57
+        assert_eq!(mod_pow(123_456_789, p as u64 - 1, p), 1);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(&[]), Some((0, 1)));
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+
+        // non-coprime moduli
+        assert_eq!(crt(&[(2, 4), (4, 6)]), Some((10, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+
+        // negative remainders
+        assert_eq!(crt(&[(0, 7), (-1, 13), (-4, 59), (-6, 31), (-7, 19)]), Some((1068781, 3162341)));
+
+        // the combined modulus doesn't fit in an i64
+        let p = 4_611_686_018_427_387_847; // 2^62 - 57
+        assert_eq!(crt(&[(1, p), (2, 3)]), None);
+        assert_eq!(crt(&[(1, 1 << 40), (2, (1 << 40) - 1)]), None);
+
+        // but a large one that does is fine, including while moduli share factors
+        assert_eq!(crt(&[(1, p), (1, p)]), Some((1, p)));
+        assert_eq!(crt(&[(5, 1 << 61), (1, 4)]), Some((5, 1 << 61)));
+        assert_eq!(crt(&[(5, 1 << 61), (5, 1 << 62)]), Some((5, 1 << 62)));
+        assert_eq!(crt(&[(5, 1 << 61), (1, 1 << 62)]), None);
+    }
+
+    #[test]
+    fn test_discrete_log() {
+        assert_eq!(discrete_log(7, 5764801, 20201227), Some(8));
+        assert_eq!(discrete_log(7, 17807724, 20201227), Some(11));
+        assert_eq!(discrete_log(2, 1, 11), Some(0));
+        assert_eq!(discrete_log(2, 3, 7), None);
+
+        for e in 0..100 {
+            let target = mod_pow(2, e, 101);
+            assert_eq!(discrete_log(2, target, 101), Some(e));
+        }
+    }
+}